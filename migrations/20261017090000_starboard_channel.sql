@@ -0,0 +1,4 @@
+-- track the channel each starboard'd message was posted in, so its starboard post can be
+-- withdrawn when the channel is deleted
+-- messages starboard'd before this migration will have a NULL channel_id until their reactions next change
+ALTER TABLE starboard ADD COLUMN channel_id INTEGER;
@@ -0,0 +1,17 @@
+-- messages are now tracked per starboard, so that one message can be posted to several boards
+-- existing posts belong to the "default" board, which is the board used when STARBOARDS is not set
+CREATE TABLE IF NOT EXISTS starboard_board
+(
+	message_id		INTEGER			NOT NULL,
+	board			TEXT			NOT NULL,
+	starboard_id	INTEGER			NOT NULL,
+	channel_id		INTEGER,
+	PRIMARY KEY (message_id, board)
+);
+
+INSERT INTO starboard_board (message_id, board, starboard_id, channel_id)
+SELECT message_id, 'default', starboard_id, channel_id
+FROM starboard;
+
+DROP TABLE starboard;
+ALTER TABLE starboard_board RENAME TO starboard;
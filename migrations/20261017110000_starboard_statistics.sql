@@ -0,0 +1,7 @@
+-- record details of each starboard post, used for leaderboards and statistics
+-- message_timestamp is the time the original message was sent, as unix epoch (in UTC milliseconds)
+-- posts made before this migration have their details filled in once their reactions next change
+ALTER TABLE starboard ADD COLUMN author_id INTEGER;
+ALTER TABLE starboard ADD COLUMN emoji TEXT;
+ALTER TABLE starboard ADD COLUMN reaction_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE starboard ADD COLUMN message_timestamp INTEGER;
@@ -0,0 +1,8 @@
+-- progress of backfilling the starboard from the history of a channel
+-- last_message_id is the newest message evaluated so far, or NULL if the backfill has not started
+CREATE TABLE IF NOT EXISTS starboard_backfill
+(
+	channel_id			INTEGER		PRIMARY KEY NOT NULL,
+	last_message_id		INTEGER,
+	completed			BOOLEAN		NOT NULL DEFAULT FALSE
+);
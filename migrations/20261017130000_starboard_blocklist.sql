@@ -0,0 +1,8 @@
+-- users, channels and messages that are never posted to the starboard
+-- kind is one of "user", "channel" or "message", and target_id is the ID of the blocked user, channel or message
+CREATE TABLE IF NOT EXISTS starboard_blocklist
+(
+	kind			TEXT		NOT NULL,
+	target_id		INTEGER		NOT NULL,
+	PRIMARY KEY (kind, target_id)
+);
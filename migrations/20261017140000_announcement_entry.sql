@@ -0,0 +1,17 @@
+-- remember every entry of each announcement feed, so that each is posted exactly once
+-- content_hash is the SHA-256 of the entry's title and body, used to notice when an entry is edited
+-- posted_time is when the entry was first seen, as unix epoch (in UTC milliseconds)
+CREATE TABLE IF NOT EXISTS announcement_entry
+(
+	feed_id			TEXT		NOT NULL,
+	entry_id		TEXT		NOT NULL,
+	content_hash	TEXT		NOT NULL,
+	posted_time		INTEGER		NOT NULL,
+	PRIMARY KEY (feed_id, entry_id)
+);
+
+-- entries seen before this migration were not recorded individually, so feeds that were already
+-- read remember when they were last read as their legacy_cutoff. Until a check of the feed has
+-- gone through all of its entries, only entries updated after that time are posted, as before
+ALTER TABLE announcement_feed ADD COLUMN legacy_cutoff INTEGER;
+UPDATE announcement_feed SET legacy_cutoff = last_updated_time;
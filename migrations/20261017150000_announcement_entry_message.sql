@@ -0,0 +1,4 @@
+-- remember the Discord message each entry was announced with, so that it can be edited along with the entry
+-- entries recorded without being posted (such as when a feed is first read) have no message
+ALTER TABLE announcement_entry ADD COLUMN channel_id INTEGER;
+ALTER TABLE announcement_entry ADD COLUMN message_id INTEGER;
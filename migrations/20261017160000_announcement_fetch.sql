@@ -0,0 +1,8 @@
+-- the HTTP cache validators each feed URL was last fetched with
+-- sent back on the next fetch so that the server can reply that the feed has not changed
+CREATE TABLE IF NOT EXISTS announcement_fetch
+(
+	url				TEXT		PRIMARY KEY NOT NULL,
+	etag			TEXT,
+	last_modified	TEXT
+);
@@ -0,0 +1,12 @@
+-- the announcement feeds to check, managed at runtime with the /feed command
+-- seeded from CANVAS_ANNOUNCEMENT_URLS while the table is empty
+-- check_interval is in seconds, and falls back to ANNOUNCEMENT_CHECK_INTERVAL when null
+CREATE TABLE IF NOT EXISTS announcement_source
+(
+	url				TEXT		PRIMARY KEY NOT NULL,
+	channel_id		INTEGER		NOT NULL,
+	role_id			INTEGER,
+	label			TEXT,
+	check_interval	INTEGER,
+	enabled			BOOLEAN		NOT NULL DEFAULT TRUE
+);
@@ -0,0 +1,22 @@
+-- announcements waiting to be posted to (or edited on) Discord, so that none are lost if Discord is unreachable
+-- kind is either "create" or "edit", and message_id is the message to edit
+-- payload is the JSON of the message content and embeds
+-- status is either "pending" or "failed", where failed announcements are not retried
+-- announcements are removed once they have been delivered
+-- times are unix epoch (in UTC milliseconds)
+CREATE TABLE IF NOT EXISTS announcement_outbox
+(
+	id					INTEGER		PRIMARY KEY AUTOINCREMENT NOT NULL,
+	feed_id				TEXT		NOT NULL,
+	entry_id			TEXT		NOT NULL,
+	kind				TEXT		NOT NULL,
+	channel_id			INTEGER		NOT NULL,
+	message_id			INTEGER,
+	payload				TEXT		NOT NULL,
+	status				TEXT		NOT NULL DEFAULT 'pending',
+	attempts			INTEGER		NOT NULL DEFAULT 0,
+	next_attempt_time	INTEGER		NOT NULL,
+	last_error			TEXT
+);
+
+CREATE INDEX IF NOT EXISTS announcement_outbox_pending ON announcement_outbox (status, next_attempt_time);
@@ -0,0 +1,14 @@
+-- rules deciding which entries of an announcement feed are announced
+-- action is 'include' or 'exclude', and kind is 'keyword', 'regex' or 'author'
+-- when a feed has include rules, only entries matching one of them are announced
+-- entries matching an exclude rule are never announced
+CREATE TABLE IF NOT EXISTS announcement_filter
+(
+	id			INTEGER		PRIMARY KEY AUTOINCREMENT,
+	url			TEXT		NOT NULL REFERENCES announcement_source (url) ON DELETE CASCADE,
+	action		TEXT		NOT NULL,
+	kind		TEXT		NOT NULL,
+	pattern		TEXT		NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS announcement_filter_url ON announcement_filter (url);
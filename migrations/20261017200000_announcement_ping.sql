@@ -0,0 +1,11 @@
+-- roles pinged for the announcements matching a rule, on top of the role of the feed itself
+-- kind is 'keyword', 'regex' or 'author', matched the same way as announcement_filter
+-- rules without a url apply to every feed
+CREATE TABLE IF NOT EXISTS announcement_ping
+(
+	id			INTEGER		PRIMARY KEY AUTOINCREMENT,
+	url			TEXT		REFERENCES announcement_source (url) ON DELETE CASCADE,
+	kind		TEXT		NOT NULL,
+	pattern		TEXT		NOT NULL,
+	role_id		INTEGER		NOT NULL
+);
@@ -0,0 +1,9 @@
+-- feeds with a thread_archive_duration open a discussion thread on each announcement they post
+-- the duration is in minutes of inactivity, and is one of 60, 1440, 4320 or 10080
+ALTER TABLE announcement_source ADD COLUMN thread_archive_duration INTEGER;
+
+-- the thread opened on the announcement of an entry, which edits of the entry are noted in
+ALTER TABLE announcement_entry ADD COLUMN thread_id INTEGER;
+
+-- the thread of the announcement an edit applies to, if it has one
+ALTER TABLE announcement_outbox ADD COLUMN thread_id INTEGER;
@@ -0,0 +1,17 @@
+-- feeds with webhook set are posted through a webhook of their channel, under their own name and avatar
+-- webhook_name falls back to the label of the feed, then to the course in the title of the feed
+ALTER TABLE announcement_source ADD COLUMN webhook BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE announcement_source ADD COLUMN webhook_name TEXT;
+ALTER TABLE announcement_source ADD COLUMN webhook_avatar_url TEXT;
+
+-- the webhook the bot posts announcements with in each channel, created the first time it is needed
+CREATE TABLE IF NOT EXISTS announcement_webhook
+(
+	channel_id		INTEGER		PRIMARY KEY NOT NULL,
+	webhook_id		INTEGER		NOT NULL,
+	token			TEXT		NOT NULL
+);
+
+-- the webhook an announcement was posted with, as only the webhook can edit it
+ALTER TABLE announcement_entry ADD COLUMN webhook_id INTEGER;
+ALTER TABLE announcement_outbox ADD COLUMN webhook_id INTEGER;
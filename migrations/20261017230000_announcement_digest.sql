@@ -0,0 +1,17 @@
+-- feeds with a digest schedule gather their new entries into a single message per channel,
+-- either 'hourly' or 'daily HH:MM' in DIGEST_TIMEZONE
+ALTER TABLE announcement_source ADD COLUMN digest TEXT;
+
+-- entries waiting to be posted in the next digest of their channel
+-- due_time is in unix milliseconds, and payload is the JSON summary of the entry
+CREATE TABLE IF NOT EXISTS announcement_digest
+(
+	id				INTEGER		PRIMARY KEY AUTOINCREMENT,
+	feed_id			TEXT		NOT NULL,
+	entry_id		TEXT		NOT NULL,
+	channel_id		INTEGER		NOT NULL,
+	due_time		INTEGER		NOT NULL,
+	payload			TEXT		NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS announcement_digest_due_time ON announcement_digest (due_time);
@@ -0,0 +1,456 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use error_stack::{Report, ResultExt};
+use feed_rs::model::{Entry, Feed};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use twilight_model::{
+    channel::message::{embed::EmbedField, Embed},
+    id::{marker::ChannelMarker, Id},
+};
+
+use crate::{
+    announcement_media::{extract_media, strip_images},
+    announcement_outbox::{enqueue_announcement, wake_outbox, Delivery, OutboxMessage},
+    config::AnnouncementFeed,
+    error::RssError,
+    markdown::{escape_mentions, length, sanitize_markdown, truncate_markdown},
+    rss_announcements::{course_name, entry_body},
+};
+
+/// The most characters of an entry's body shown in a digest.
+const MAX_SUMMARY_LENGTH: usize = 300;
+
+/// The most characters Discord allows in the name of an embed field.
+const MAX_FIELD_NAME_LENGTH: usize = 256;
+
+/// The most characters Discord allows in the value of an embed field.
+const MAX_FIELD_LENGTH: usize = 1024;
+
+/// The most fields Discord allows on an embed.
+const MAX_FIELDS: usize = 25;
+
+/// The most embeds Discord allows on a message.
+const MAX_EMBEDS: usize = 10;
+
+/// The most characters Discord allows across all embeds of a message.
+const MAX_EMBED_TOTAL_LENGTH: usize = 6000;
+
+/// The title of the first embed of each message of a digest.
+const DIGEST_TITLE: &str = "Announcement digest";
+
+/// The longest to wait before checking for digests to post, in case a wake up is missed.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Lets the poster know that entries were added to a digest.
+static DIGEST_CHANGED: tokio::sync::Notify = tokio::sync::Notify::const_new();
+
+/// An entry of a feed, summarised for a digest.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DigestItem {
+    /// The title of the entry.
+    pub title: String,
+    /// The link to the entry, if it has one.
+    pub url: Option<String>,
+    /// The course or feed the entry belongs to.
+    pub source: String,
+    /// The start of the body of the entry.
+    pub summary: String,
+}
+
+impl DigestItem {
+    /// Summarises an `entry` of the `feed` announced by `announcement`.
+    pub fn new(announcement: &AnnouncementFeed, feed: &Feed, entry: &Entry) -> Self {
+        let html = entry_body(entry).unwrap_or_default();
+        // images are left out, as digests only summarise the text of entries
+        let media = extract_media(entry, html);
+        let body = strip_images(&html2md::parse_html(html), &media.images);
+
+        Self {
+            title: entry
+                .title
+                .as_ref()
+                .map(|title| escape_mentions(&title.content))
+                .unwrap_or_else(|| "Untitled announcement".to_string()),
+            url: entry.links.first().map(|link| link.href.clone()),
+            source: announcement
+                .label
+                .clone()
+                .or_else(|| course_name(feed))
+                .unwrap_or_else(|| announcement.url.clone()),
+            summary: truncate_markdown(sanitize_markdown(&body).trim(), MAX_SUMMARY_LENGTH),
+        }
+    }
+
+    /// The field the entry is shown with in a digest.
+    fn field(&self) -> EmbedField {
+        let link = self
+            .url
+            .as_ref()
+            .map(|url| format!("\n[Read the announcement]({url})"))
+            .unwrap_or_default();
+        let summary = truncate_markdown(&self.summary, MAX_FIELD_LENGTH - length(&link));
+
+        EmbedField {
+            inline: false,
+            name: truncate_markdown(
+                &format!("{}: {}", self.source, self.title),
+                MAX_FIELD_NAME_LENGTH,
+            ),
+            // fields must have a value
+            value: match (summary.trim().is_empty(), link.is_empty()) {
+                (true, true) => "\u{200B}".to_string(),
+                _ => format!("{summary}{link}").trim().to_string(),
+            },
+        }
+    }
+}
+
+/// Adds an entry to the next digest of the `channel_id`, which is posted at the `due_time`.
+///
+/// The `connection` may be a transaction, so that the entry is only added along with it being
+/// recorded as seen. The poster is woken up by [`wake_digests`].
+pub async fn enqueue_digest_item(
+    feed_id: &str,
+    entry_id: &str,
+    channel_id: Id<ChannelMarker>,
+    due_time: DateTime<Utc>,
+    item: &DigestItem,
+    connection: &mut SqliteConnection,
+) -> Result<(), Report<RssError>> {
+    let channel_id = channel_id.to_string();
+    let due_time = due_time.timestamp_millis();
+    let payload = serde_json::to_string(item).change_context(RssError::Database)?;
+
+    sqlx::query!(
+        r#"
+INSERT INTO announcement_digest (feed_id, entry_id, channel_id, due_time, payload)
+VALUES (?, ?, ?, ?, ?)
+	"#,
+        feed_id,
+        entry_id,
+        channel_id,
+        due_time,
+        payload
+    )
+    .execute(&mut *connection)
+    .await
+    .change_context(RssError::Database)?;
+
+    Ok(())
+}
+
+/// Replaces the summary of an entry waiting for its digest, so that an entry edited before its
+/// digest is posted is summarised as it is now.
+///
+/// Returns `false` if the entry is not waiting for a digest.
+pub async fn replace_digest_item(
+    feed_id: &str,
+    entry_id: &str,
+    item: &DigestItem,
+    connection: &mut SqliteConnection,
+) -> Result<bool, Report<RssError>> {
+    let payload = serde_json::to_string(item).change_context(RssError::Database)?;
+
+    let result = sqlx::query!(
+        r#"
+UPDATE announcement_digest
+SET payload = ?
+WHERE feed_id = ? AND entry_id = ?
+	"#,
+        payload,
+        feed_id,
+        entry_id
+    )
+    .execute(&mut *connection)
+    .await
+    .change_context(RssError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Lets the poster know that entries were added to a digest.
+pub fn wake_digests() {
+    DIGEST_CHANGED.notify_one();
+}
+
+/// Posts the digests of each channel once they are due, for as long as the bot runs.
+///
+/// Digests are handed to the outbox to be delivered, along with the entries they summarise being
+/// removed, so that each entry is posted in exactly one digest.
+pub async fn post_digests(pool: SqlitePool) -> Result<(), Report<RssError>> {
+    loop {
+        let current_time = Utc::now().timestamp_millis();
+
+        let due = sqlx::query!(
+            r#"
+SELECT id, channel_id, payload
+FROM announcement_digest
+WHERE due_time <= ?
+ORDER BY id
+	"#,
+            current_time
+        )
+        .fetch_all(&pool)
+        .await
+        .change_context(RssError::Database)?;
+
+        // entries from every feed of a channel are gathered into the same digest
+        let mut channels = BTreeMap::<i64, Vec<DigestItem>>::new();
+        for row in &due {
+            match serde_json::from_str::<DigestItem>(&row.payload) {
+                Ok(item) => channels.entry(row.channel_id).or_default().push(item),
+                Err(error) => log::error!("Skipping invalid digest entry {}: {error}", row.id),
+            }
+        }
+
+        if !due.is_empty() {
+            let mut transaction = pool.begin().await.change_context(RssError::Database)?;
+
+            for (channel_id, items) in channels {
+                let Some(channel_id) = u64::try_from(channel_id).ok().and_then(Id::new_checked)
+                else {
+                    log::error!("Skipping digest for invalid channel {channel_id}");
+                    continue;
+                };
+
+                let count = items.len();
+                for (i, message) in digest_messages(&items).into_iter().enumerate() {
+                    // digests are not entries of a feed, so they are never edited
+                    enqueue_announcement(
+                        "digest",
+                        &format!("{channel_id}:{current_time}:{i}"),
+                        Delivery::Create { channel_id },
+                        &message,
+                        &mut transaction,
+                    )
+                    .await?;
+                }
+
+                log::info!("Posting a digest of {count} announcements in channel {channel_id}");
+            }
+
+            // entries added since the digest was gathered are left for the next one
+            let last_id = due.iter().map(|row| row.id).max();
+            sqlx::query!(
+                r#"
+DELETE FROM announcement_digest
+WHERE due_time <= ? AND id <= ?
+	"#,
+                current_time,
+                last_id
+            )
+            .execute(&mut *transaction)
+            .await
+            .change_context(RssError::Database)?;
+
+            transaction
+                .commit()
+                .await
+                .change_context(RssError::Database)?;
+            wake_outbox();
+        }
+
+        // sleep until the next digest is due, or entries are added to one
+        let next_due_time = sqlx::query!(
+            r#"
+SELECT MIN(due_time) AS "due_time: i64"
+FROM announcement_digest
+	"#
+        )
+        .fetch_one(&pool)
+        .await
+        .change_context(RssError::Database)?
+        .due_time;
+
+        let wait = next_due_time
+            .map(|due_time| {
+                let millis = due_time - Utc::now().timestamp_millis();
+                Duration::from_millis(millis.max(0) as u64)
+            })
+            .unwrap_or(POLL_INTERVAL)
+            .min(POLL_INTERVAL);
+
+        tokio::select! {
+            _ = DIGEST_CHANGED.notified() => {}
+            _ = tokio::time::sleep(wait) => {}
+        }
+    }
+}
+
+/// Builds the messages a digest of `items` is posted with, with a field for each item.
+///
+/// Items are spread across as many embeds, and messages, as Discord requires.
+fn digest_messages(items: &[DigestItem]) -> Vec<OutboxMessage> {
+    let mut messages = vec![];
+    let mut embeds = vec![];
+    let mut fields = vec![];
+    // the characters of the current message so far, starting with the title of its first embed
+    let mut total = length(DIGEST_TITLE);
+
+    for item in items {
+        let field = item.field();
+        let field_length = length(&field.name) + length(&field.value);
+
+        if fields.len() == MAX_FIELDS || total + field_length > MAX_EMBED_TOTAL_LENGTH {
+            embeds.push(digest_embed(std::mem::take(&mut fields), embeds.is_empty()));
+
+            if embeds.len() == MAX_EMBEDS || total + field_length > MAX_EMBED_TOTAL_LENGTH {
+                messages.push(digest_message(std::mem::take(&mut embeds)));
+                total = length(DIGEST_TITLE);
+            }
+        }
+
+        total += field_length;
+        fields.push(field);
+    }
+
+    if !fields.is_empty() {
+        embeds.push(digest_embed(fields, embeds.is_empty()));
+    }
+    if !embeds.is_empty() {
+        messages.push(digest_message(embeds));
+    }
+
+    messages
+}
+
+/// An embed of a digest, which is titled if it is the first embed of its message.
+fn digest_embed(fields: Vec<EmbedField>, first: bool) -> Embed {
+    Embed {
+        author: None,
+        color: Some(15844367),
+        description: None,
+        fields,
+        footer: None,
+        timestamp: None,
+        kind: "rich".to_string(),
+        image: None,
+        provider: None,
+        thumbnail: None,
+        title: first.then(|| DIGEST_TITLE.to_string()),
+        url: None,
+        video: None,
+    }
+}
+
+/// A message of a digest, which pings nobody.
+fn digest_message(embeds: Vec<Embed>) -> OutboxMessage {
+    OutboxMessage {
+        content: String::new(),
+        embeds,
+        files: vec![],
+        roles: vec![],
+        thread: None,
+        webhook: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(i: usize, summary_length: usize) -> DigestItem {
+        DigestItem {
+            title: format!("Announcement {i}"),
+            url: Some(format!("https://example.com/announcements/{i}")),
+            source: "CS 101".to_string(),
+            summary: "x".repeat(summary_length),
+        }
+    }
+
+    /// The characters of a message that count towards the total Discord allows across its embeds.
+    fn message_length(message: &OutboxMessage) -> usize {
+        message
+            .embeds
+            .iter()
+            .map(|embed| {
+                embed.title.as_deref().map(length).unwrap_or_default()
+                    + embed
+                        .fields
+                        .iter()
+                        .map(|field| length(&field.name) + length(&field.value))
+                        .sum::<usize>()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn fields_link_to_the_announcement() {
+        let field = item(1, 10).field();
+
+        assert_eq!(field.name, "CS 101: Announcement 1");
+        assert_eq!(
+            field.value,
+            "xxxxxxxxxx\n[Read the announcement](https://example.com/announcements/1)"
+        );
+    }
+
+    #[test]
+    fn fields_always_have_a_value() {
+        let item = DigestItem {
+            url: None,
+            ..item(1, 0)
+        };
+
+        assert!(!item.field().value.is_empty());
+    }
+
+    #[test]
+    fn small_digests_are_one_embed() {
+        let items = (0..3).map(|i| item(i, 50)).collect::<Vec<_>>();
+        let messages = digest_messages(&items);
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].embeds.len(), 1);
+        assert_eq!(messages[0].embeds[0].fields.len(), 3);
+        assert_eq!(messages[0].embeds[0].title.as_deref(), Some(DIGEST_TITLE));
+    }
+
+    #[test]
+    fn digests_are_spread_across_embeds_and_messages() {
+        let items = (0..200)
+            .map(|i| item(i, MAX_SUMMARY_LENGTH))
+            .collect::<Vec<_>>();
+        let messages = digest_messages(&items);
+
+        assert!(messages.len() > 1);
+        for message in &messages {
+            assert!(message.embeds.len() <= MAX_EMBEDS);
+            assert!(message_length(message) <= MAX_EMBED_TOTAL_LENGTH);
+            assert!(message
+                .embeds
+                .iter()
+                .all(|embed| !embed.fields.is_empty() && embed.fields.len() <= MAX_FIELDS));
+            // only the first embed of each message is titled
+            assert!(message.embeds[0].title.is_some());
+            assert!(message.embeds[1..]
+                .iter()
+                .all(|embed| embed.title.is_none()));
+        }
+
+        let fields = messages
+            .iter()
+            .flat_map(|message| &message.embeds)
+            .map(|embed| embed.fields.len())
+            .sum::<usize>();
+        assert_eq!(fields, items.len());
+    }
+
+    #[test]
+    fn many_short_items_respect_the_field_limit() {
+        let items = (0..60).map(|i| item(i, 0)).collect::<Vec<_>>();
+        let messages = digest_messages(&items);
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            messages[0]
+                .embeds
+                .iter()
+                .map(|embed| embed.fields.len())
+                .collect::<Vec<_>>(),
+            vec![25, 25, 10]
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use error_stack::{Report, ResultExt};
+use sqlx::SqlitePool;
+use twilight_model::{channel::thread::AutoArchiveDuration, id::Id};
+
+use crate::{
+    config::{AnnouncementFeed, DigestSchedule, WebhookIdentity},
+    error::RssError,
+};
+
+/// An announcement feed stored in the database.
+#[derive(Debug, Clone)]
+pub struct StoredFeed {
+    /// The feed and where it is announced.
+    pub feed: AnnouncementFeed,
+    /// Whether the feed is checked for announcements, or has been paused.
+    pub enabled: bool,
+}
+
+/// Stores the feeds configured in the environment, unless feeds are already stored.
+///
+/// Once stored, feeds are managed with the `/feed` command instead, so the environment is only used
+/// to carry feeds over from before they were stored in the database.
+pub async fn seed_feeds(
+    feeds: &[AnnouncementFeed],
+    pool: &SqlitePool,
+) -> Result<(), Report<RssError>> {
+    let stored = sqlx::query!(
+        r#"
+SELECT COUNT(*) AS "count!: i64"
+FROM announcement_source
+	"#
+    )
+    .fetch_one(pool)
+    .await
+    .change_context(RssError::Database)?
+    .count;
+
+    if stored > 0 {
+        return Ok(());
+    }
+
+    let mut added = 0;
+    for feed in feeds {
+        match add_feed(feed, pool).await? {
+            true => added += 1,
+            false => log::warn!(
+                "Skipping feed {} in <#{}> from the environment, as it is already announced in another channel",
+                feed.url,
+                feed.channel_id
+            ),
+        }
+    }
+    log::info!("Stored {added} announcement feeds from the environment");
+
+    Ok(())
+}
+
+/// Retrieves every stored feed, whether it is enabled or not.
+pub async fn list_feeds(pool: &SqlitePool) -> Result<Vec<StoredFeed>, Report<RssError>> {
+    sqlx::query!(
+        r#"
+SELECT
+	url, channel_id, role_id, label, check_interval, thread_archive_duration,
+	webhook AS "webhook: bool", webhook_name, webhook_avatar_url, digest, enabled AS "enabled: bool"
+FROM announcement_source
+ORDER BY label, url
+	"#
+    )
+    .fetch_all(pool)
+    .await
+    .change_context(RssError::Database)?
+    .into_iter()
+    .map(|row| {
+        Ok(StoredFeed {
+            feed: AnnouncementFeed {
+                channel_id: u64::try_from(row.channel_id)
+                    .ok()
+                    .and_then(Id::new_checked)
+                    .ok_or(RssError::Database)
+                    .attach_with(|| format!("Invalid channel of feed {}", row.url))?,
+                role_id: row
+                    .role_id
+                    .and_then(|role_id| u64::try_from(role_id).ok())
+                    .and_then(Id::new_checked),
+                label: row.label,
+                check_interval: row
+                    .check_interval
+                    .and_then(|secs| u64::try_from(secs).ok())
+                    // an interval of zero would check the feed nonstop, so use the default instead
+                    .filter(|secs| *secs > 0)
+                    .map(Duration::from_secs),
+                thread_archive_duration: row
+                    .thread_archive_duration
+                    .and_then(|minutes| u16::try_from(minutes).ok())
+                    .map(AutoArchiveDuration::from),
+                webhook: row.webhook.then_some(WebhookIdentity {
+                    name: row.webhook_name,
+                    avatar_url: row.webhook_avatar_url,
+                }),
+                digest: row.digest.as_deref().and_then(DigestSchedule::parse),
+                url: row.url,
+            },
+            enabled: row.enabled,
+        })
+    })
+    .collect()
+}
+
+/// Stores a new feed, or replaces the stored feed with the same URL and channel.
+///
+/// Each feed is announced in a single channel, so returns `false` without changing anything if the
+/// feed with the URL is already announced in another channel.
+pub async fn add_feed(
+    feed: &AnnouncementFeed,
+    pool: &SqlitePool,
+) -> Result<bool, Report<RssError>> {
+    let channel_id = feed.channel_id.to_string();
+    let role_id = feed.role_id.map(|role_id| role_id.to_string());
+    let check_interval = feed
+        .check_interval
+        .map(|check_interval| check_interval.as_secs() as i64);
+    let thread_archive_duration = feed
+        .thread_archive_duration
+        .map(|duration| duration.number() as i64);
+    let webhook = feed.webhook.is_some();
+    let digest = feed.digest.map(|digest| digest.to_string());
+    let webhook_name = feed
+        .webhook
+        .as_ref()
+        .and_then(|webhook| webhook.name.as_ref());
+    let webhook_avatar_url = feed
+        .webhook
+        .as_ref()
+        .and_then(|webhook| webhook.avatar_url.as_ref());
+
+    let result = sqlx::query!(
+        r#"
+INSERT INTO announcement_source (
+	url, channel_id, role_id, label, check_interval, thread_archive_duration,
+	webhook, webhook_name, webhook_avatar_url, digest
+)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+ON CONFLICT (url) DO UPDATE SET
+	role_id = excluded.role_id,
+	label = excluded.label,
+	check_interval = excluded.check_interval,
+	thread_archive_duration = excluded.thread_archive_duration,
+	webhook = excluded.webhook,
+	webhook_name = excluded.webhook_name,
+	webhook_avatar_url = excluded.webhook_avatar_url,
+	digest = excluded.digest
+WHERE announcement_source.channel_id = excluded.channel_id
+	"#,
+        feed.url,
+        channel_id,
+        role_id,
+        feed.label,
+        check_interval,
+        thread_archive_duration,
+        webhook,
+        webhook_name,
+        webhook_avatar_url,
+        digest
+    )
+    .execute(pool)
+    .await
+    .change_context(RssError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Forgets about the feed with the `url`.
+///
+/// Returns `false` if there was no such feed.
+pub async fn remove_feed(url: &str, pool: &SqlitePool) -> Result<bool, Report<RssError>> {
+    let result = sqlx::query!(
+        r#"
+DELETE FROM announcement_source
+WHERE url = ?
+	"#,
+        url
+    )
+    .execute(pool)
+    .await
+    .change_context(RssError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Pauses or resumes checking the feed with the `url`.
+///
+/// Returns `false` if there was no such feed.
+pub async fn set_feed_enabled(
+    url: &str,
+    enabled: bool,
+    pool: &SqlitePool,
+) -> Result<bool, Report<RssError>> {
+    let result = sqlx::query!(
+        r#"
+UPDATE announcement_source
+SET enabled = ?
+WHERE url = ?
+	"#,
+        enabled,
+        url
+    )
+    .execute(pool)
+    .await
+    .change_context(RssError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,434 @@
+use std::{fmt::Display, str::FromStr};
+
+use error_stack::{Report, ResultExt};
+use feed_rs::model::Entry;
+use regex::Regex;
+use sqlx::SqlitePool;
+
+use crate::{error::RssError, rss_announcements::entry_body};
+
+/// What happens to the entries a filter rule matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterAction {
+    /// Only entries matching an include rule are announced.
+    Include,
+    /// Entries matching an exclude rule are never announced.
+    Exclude,
+}
+
+/// What a filter rule matches entries by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterKind {
+    /// Text that appears in the title or body of the entry, ignoring case.
+    Keyword,
+    /// A regular expression matching the title or body of the entry.
+    Regex,
+    /// The name of an author of the entry, ignoring case.
+    Author,
+}
+
+impl FilterAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Include => "include",
+            Self::Exclude => "exclude",
+        }
+    }
+}
+
+impl FromStr for FilterAction {
+    type Err = Report<RssError>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "include" => Ok(Self::Include),
+            "exclude" => Ok(Self::Exclude),
+            _ => Err(Report::new(RssError::Filter)).attach(format!("Unknown filter action {s}")),
+        }
+    }
+}
+
+impl FilterKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword",
+            Self::Regex => "regex",
+            Self::Author => "author",
+        }
+    }
+}
+
+impl FromStr for FilterKind {
+    type Err = Report<RssError>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keyword" => Ok(Self::Keyword),
+            "regex" => Ok(Self::Regex),
+            "author" => Ok(Self::Author),
+            _ => Err(Report::new(RssError::Filter)).attach(format!("Unknown filter kind {s}")),
+        }
+    }
+}
+
+/// A rule deciding whether the entries of a feed are announced.
+#[derive(Debug, Clone)]
+pub struct FilterRule {
+    /// Identifies the rule, so that it can be removed.
+    pub id: i64,
+    pub action: FilterAction,
+    pub kind: FilterKind,
+    pub pattern: String,
+}
+
+impl Display for FilterRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} {} {} `{}`",
+            self.id,
+            self.action.as_str(),
+            self.kind.as_str(),
+            self.pattern.replace('`', "'")
+        )
+    }
+}
+
+/// Why an entry is not announced.
+#[derive(Debug)]
+pub enum Filtered<'a> {
+    /// The entry matches an exclude rule.
+    Excluded(&'a FilterRule),
+    /// The feed has include rules, and the entry matches none of them.
+    NotIncluded,
+}
+
+impl Display for Filtered<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Excluded(rule) => write!(f, "matches {rule}"),
+            Self::NotIncluded => write!(f, "matches no include rule"),
+        }
+    }
+}
+
+/// The text of an entry that rules are matched against.
+pub struct EntryText<'a> {
+    entry: &'a Entry,
+    text: String,
+    lowercase_text: String,
+}
+
+impl<'a> EntryText<'a> {
+    /// Gathers the title and body of an `entry`, with the body converted to Markdown.
+    pub fn new(entry: &'a Entry) -> Self {
+        let text = format!(
+            "{}\n{}",
+            entry
+                .title
+                .as_ref()
+                .map(|title| title.content.as_str())
+                .unwrap_or_default(),
+            entry_body(entry)
+                .map(html2md::parse_html)
+                .unwrap_or_default()
+        );
+
+        Self {
+            entry,
+            lowercase_text: text.to_lowercase(),
+            text,
+        }
+    }
+
+    /// Whether the entry matches the `pattern`, given its `regex` if it is a regular expression.
+    pub fn matches(&self, kind: FilterKind, pattern: &str, regex: Option<&Regex>) -> bool {
+        match (kind, regex) {
+            (FilterKind::Keyword, _) => self.lowercase_text.contains(&pattern.to_lowercase()),
+            (FilterKind::Regex, Some(regex)) => regex.is_match(&self.text),
+            (FilterKind::Regex, None) => false,
+            (FilterKind::Author, _) => self
+                .entry
+                .authors
+                .iter()
+                .any(|author| author.name.eq_ignore_ascii_case(pattern.trim())),
+        }
+    }
+}
+
+/// Compiles the `pattern` of a rule if it is a regular expression.
+pub fn compile_pattern(kind: FilterKind, pattern: &str) -> Result<Option<Regex>, Report<RssError>> {
+    match kind {
+        FilterKind::Regex => Regex::new(pattern)
+            .map(Some)
+            .change_context(RssError::Filter)
+            .attach_with(|| format!("Invalid regular expression `{pattern}`")),
+        _ => Ok(None),
+    }
+}
+
+/// The filter rules of a feed, ready to be applied to its entries.
+#[derive(Debug, Default)]
+pub struct FeedFilter {
+    rules: Vec<(FilterRule, Option<Regex>)>,
+}
+
+impl FeedFilter {
+    /// Prepares the `rules` of a feed, compiling their regular expressions.
+    pub fn new(rules: Vec<FilterRule>) -> Result<Self, Report<RssError>> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let regex = compile_pattern(rule.kind, &rule.pattern)
+                    .attach_with(|| format!("Invalid filter rule {rule}"))?;
+                Ok((rule, regex))
+            })
+            .collect::<Result<_, Report<RssError>>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Whether the filter has any rules at all.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Decides whether an entry is announced, returning why it is not otherwise.
+    ///
+    /// Exclude rules take precedence over include rules.
+    pub fn check(&self, entry: &Entry) -> Result<(), Filtered<'_>> {
+        if self.rules.is_empty() {
+            return Ok(());
+        }
+
+        let text = EntryText::new(entry);
+        let matches = |rule: &FilterRule, regex: &Option<Regex>| {
+            text.matches(rule.kind, &rule.pattern, regex.as_ref())
+        };
+
+        if let Some((rule, _)) = self
+            .rules
+            .iter()
+            .find(|(rule, regex)| rule.action == FilterAction::Exclude && matches(rule, regex))
+        {
+            return Err(Filtered::Excluded(rule));
+        }
+
+        let mut includes = self
+            .rules
+            .iter()
+            .filter(|(rule, _)| rule.action == FilterAction::Include)
+            .peekable();
+
+        match includes.peek().is_none() || includes.any(|(rule, regex)| matches(rule, regex)) {
+            true => Ok(()),
+            false => Err(Filtered::NotIncluded),
+        }
+    }
+}
+
+/// Retrieves the filter rules of the feed with the `url`, in the order they were added.
+pub async fn list_filters(
+    url: &str,
+    pool: &SqlitePool,
+) -> Result<Vec<FilterRule>, Report<RssError>> {
+    sqlx::query!(
+        r#"
+SELECT id AS "id!: i64", action, kind, pattern
+FROM announcement_filter
+WHERE url = ?
+ORDER BY id
+	"#,
+        url
+    )
+    .fetch_all(pool)
+    .await
+    .change_context(RssError::Database)?
+    .into_iter()
+    .map(|row| {
+        Ok(FilterRule {
+            id: row.id,
+            action: row.action.parse()?,
+            kind: row.kind.parse()?,
+            pattern: row.pattern,
+        })
+    })
+    .collect()
+}
+
+/// Retrieves the filter rules of the feed with the `url`, ready to be applied to its entries.
+pub async fn feed_filter(url: &str, pool: &SqlitePool) -> Result<FeedFilter, Report<RssError>> {
+    FeedFilter::new(list_filters(url, pool).await?)
+}
+
+/// Adds a filter rule to the feed with the `url`, returning the ID of the new rule.
+///
+/// Returns `None` if there is no such feed.
+pub async fn add_filter(
+    url: &str,
+    action: FilterAction,
+    kind: FilterKind,
+    pattern: &str,
+    pool: &SqlitePool,
+) -> Result<Option<i64>, Report<RssError>> {
+    let action = action.as_str();
+    let kind = kind.as_str();
+
+    let id = sqlx::query!(
+        r#"
+INSERT INTO announcement_filter (url, action, kind, pattern)
+SELECT url, ?, ?, ?
+FROM announcement_source
+WHERE url = ?
+RETURNING id AS "id!: i64"
+	"#,
+        action,
+        kind,
+        pattern,
+        url
+    )
+    .fetch_optional(pool)
+    .await
+    .change_context(RssError::Database)?
+    .map(|row| row.id);
+
+    Ok(id)
+}
+
+/// Removes the filter rule with the `id`.
+///
+/// Returns `false` if there was no such rule.
+pub async fn remove_filter(id: i64, pool: &SqlitePool) -> Result<bool, Report<RssError>> {
+    let result = sqlx::query!(
+        r#"
+DELETE FROM announcement_filter
+WHERE id = ?
+	"#,
+        id
+    )
+    .execute(pool)
+    .await
+    .change_context(RssError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses an Atom entry with the `title`, `author` and HTML `body`.
+    fn entry(title: &str, author: &str, body: &str) -> Entry {
+        let atom = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Announcements</title>
+  <id>tag:example.com,2026:feed</id>
+  <updated>2026-10-17T08:00:00Z</updated>
+  <entry>
+    <title>{title}</title>
+    <id>tag:example.com,2026:entry</id>
+    <updated>2026-10-17T08:00:00Z</updated>
+    <author><name>{author}</name></author>
+    <content type="html">{body}</content>
+  </entry>
+</feed>"#
+        );
+
+        feed_rs::parser::parse(atom.as_bytes())
+            .expect("valid Atom feed")
+            .entries
+            .remove(0)
+    }
+
+    fn rule(id: i64, action: FilterAction, kind: FilterKind, pattern: &str) -> FilterRule {
+        FilterRule {
+            id,
+            action,
+            kind,
+            pattern: pattern.to_string(),
+        }
+    }
+
+    #[test]
+    fn feeds_without_rules_announce_everything() {
+        let filter = FeedFilter::new(vec![]).unwrap();
+
+        assert!(filter.is_empty());
+        assert!(filter.check(&entry("Exam", "Professor", "body")).is_ok());
+    }
+
+    #[test]
+    fn keywords_ignore_case_and_match_the_body() {
+        let filter = FeedFilter::new(vec![rule(
+            1,
+            FilterAction::Exclude,
+            FilterKind::Keyword,
+            "OFFICE HOURS",
+        )])
+        .unwrap();
+
+        assert!(matches!(
+            filter.check(&entry("Update", "TA", "&lt;p&gt;Office hours are cancelled&lt;/p&gt;")),
+            Err(Filtered::Excluded(rule)) if rule.id == 1
+        ));
+        assert!(filter.check(&entry("Exam", "TA", "body")).is_ok());
+    }
+
+    #[test]
+    fn include_rules_keep_out_everything_else() {
+        let filter = FeedFilter::new(vec![
+            rule(1, FilterAction::Include, FilterKind::Regex, r"(?i)\bexam\b"),
+            rule(2, FilterAction::Include, FilterKind::Author, "professor"),
+        ])
+        .unwrap();
+
+        assert!(filter.check(&entry("Midterm exam", "TA", "body")).is_ok());
+        assert!(filter.check(&entry("Reading", "Professor", "body")).is_ok());
+        assert!(matches!(
+            filter.check(&entry("Examples", "TA", "body")),
+            Err(Filtered::NotIncluded)
+        ));
+    }
+
+    #[test]
+    fn exclude_rules_take_precedence() {
+        let filter = FeedFilter::new(vec![
+            rule(1, FilterAction::Include, FilterKind::Keyword, "exam"),
+            rule(2, FilterAction::Exclude, FilterKind::Keyword, "cancelled"),
+        ])
+        .unwrap();
+
+        assert!(matches!(
+            filter.check(&entry("Exam cancelled", "Professor", "body")),
+            Err(Filtered::Excluded(rule)) if rule.id == 2
+        ));
+    }
+
+    #[test]
+    fn invalid_regular_expressions_are_rejected() {
+        assert!(compile_pattern(FilterKind::Regex, "(unclosed").is_err());
+        assert!(FeedFilter::new(vec![rule(
+            1,
+            FilterAction::Include,
+            FilterKind::Regex,
+            "(unclosed"
+        )])
+        .is_err());
+        // other kinds are matched as they are
+        assert!(compile_pattern(FilterKind::Keyword, "(unclosed")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn actions_and_kinds_are_stored_as_they_are_parsed() {
+        for action in [FilterAction::Include, FilterAction::Exclude] {
+            assert_eq!(action.as_str().parse::<FilterAction>().unwrap(), action);
+        }
+        for kind in [FilterKind::Keyword, FilterKind::Regex, FilterKind::Author] {
+            assert_eq!(kind.as_str().parse::<FilterKind>().unwrap(), kind);
+        }
+        assert!("sometimes".parse::<FilterAction>().is_err());
+    }
+}
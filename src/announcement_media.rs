@@ -0,0 +1,281 @@
+use std::sync::LazyLock;
+
+use feed_rs::model::Entry;
+use regex::Regex;
+
+/// Matches the source of an image in HTML.
+static IMAGE_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)<img\b[^>]*?\bsrc\s*=\s*["']([^"']+)["']"#).expect("valid image regex")
+});
+
+/// Matches the target and text of a link in HTML.
+static LINK_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)<a\b[^>]*?\bhref\s*=\s*["']([^"']+)["'][^>]*>(.*?)</a>"#)
+        .expect("valid link regex")
+});
+
+/// Matches any HTML tag.
+static ANY_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<[^>]*>").expect("valid tag regex"));
+
+/// Matches the description and source of an image in Markdown.
+static MARKDOWN_IMAGE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"!\[([^\]]*)\]\(([^)\s]*)(?:\s+"[^"]*")?\)"#).expect("valid Markdown image regex")
+});
+
+/// Matches a link in Markdown without any text.
+static EMPTY_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[\s*\]\([^)]*\)").expect("valid Markdown link regex"));
+
+/// The file extensions of images that can be shown in an embed.
+const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "gif", "webp"];
+
+/// The file extensions of documents commonly attached to announcements.
+const FILE_EXTENSIONS: [&str; 14] = [
+    "pdf", "doc", "docx", "ppt", "pptx", "xls", "xlsx", "csv", "txt", "rtf", "odt", "ods", "odp",
+    "zip",
+];
+
+/// The images and files included in an announcement.
+#[derive(Debug, Default)]
+pub struct AnnouncementMedia {
+    /// The URLs of the images, in the order they appear.
+    pub images: Vec<String>,
+    /// The names and URLs of the files, in the order they appear.
+    pub files: Vec<(String, String)>,
+}
+
+impl AnnouncementMedia {
+    fn add_image(&mut self, url: String) {
+        if !self.images.contains(&url) {
+            self.images.push(url);
+        }
+    }
+
+    fn add_file(&mut self, name: String, url: String) {
+        if !self.files.iter().any(|(_, file_url)| *file_url == url) {
+            self.files.push((name, url));
+        }
+    }
+}
+
+/// The lowercase extension of the file a URL points to, ignoring its query.
+fn extension(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next()?;
+    let (_, extension) = path.rsplit_once('/')?.1.rsplit_once('.')?;
+
+    Some(extension.to_lowercase())
+}
+
+/// The name of the file a URL points to.
+fn file_name(url: &str) -> String {
+    url.split(['?', '#'])
+        .next()
+        .and_then(|path| path.trim_end_matches('/').rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("file")
+        .to_string()
+}
+
+/// Whether a link points to a file, rather than a page. Canvas serves files under `/files/`.
+fn is_file(url: &str) -> bool {
+    url.contains("/files/")
+        || extension(url).is_some_and(|extension| FILE_EXTENSIONS.contains(&extension.as_str()))
+}
+
+fn is_image(url: &str) -> bool {
+    extension(url).is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.as_str()))
+}
+
+/// Only URLs that Discord can fetch are kept.
+fn is_absolute(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+/// Finds the images and files of an entry, both in its HTML `body` and attached to it as media or
+/// enclosures.
+pub fn extract_media(entry: &Entry, body: &str) -> AnnouncementMedia {
+    let mut media = AnnouncementMedia::default();
+
+    for image in IMAGE_TAG.captures_iter(body) {
+        let url = image[1].replace("&amp;", "&");
+        if is_absolute(&url) {
+            media.add_image(url);
+        }
+    }
+
+    for link in LINK_TAG.captures_iter(body) {
+        let url = link[1].replace("&amp;", "&");
+        if !is_absolute(&url) {
+            continue;
+        }
+
+        if is_image(&url) {
+            media.add_image(url);
+        } else if is_file(&url) {
+            let name = ANY_TAG.replace_all(&link[2], "").trim().to_string();
+            let name = match name.is_empty() {
+                true => file_name(&url),
+                false => name,
+            };
+            media.add_file(name, url);
+        }
+    }
+
+    // media attached through the RSS Media spec
+    for object in &entry.media {
+        let title = object.title.as_ref().map(|title| title.content.clone());
+
+        for content in &object.content {
+            let Some(url) = content.url.as_ref().map(|url| url.to_string()) else {
+                continue;
+            };
+            let is_image_type = content
+                .content_type
+                .as_ref()
+                .map(|content_type| content_type.to_string().starts_with("image/"))
+                .unwrap_or_else(|| is_image(&url));
+
+            match is_image_type {
+                true => media.add_image(url),
+                false => media.add_file(title.clone().unwrap_or_else(|| file_name(&url)), url),
+            }
+        }
+
+        // thumbnails only stand in for media without any content of its own
+        if object.content.is_empty() {
+            for thumbnail in &object.thumbnails {
+                media.add_image(thumbnail.image.uri.clone());
+            }
+        }
+    }
+
+    // enclosures of Atom feeds
+    for link in &entry.links {
+        if link.rel.as_deref() != Some("enclosure") || !is_absolute(&link.href) {
+            continue;
+        }
+
+        let is_image_type = link
+            .media_type
+            .as_deref()
+            .map(|media_type| media_type.starts_with("image/"))
+            .unwrap_or_else(|| is_image(&link.href));
+
+        match is_image_type {
+            true => media.add_image(link.href.clone()),
+            false => media.add_file(
+                link.title.clone().unwrap_or_else(|| file_name(&link.href)),
+                link.href.clone(),
+            ),
+        }
+    }
+
+    media
+}
+
+/// Removes the `images` that are shown in embeds from Markdown, along with the links left empty by
+/// removing them. Images that cannot be shown are left as links instead.
+pub fn strip_images(markdown: &str, images: &[String]) -> String {
+    let stripped = MARKDOWN_IMAGE.replace_all(markdown, |image: &regex::Captures| {
+        let url = &image[2];
+        match images.iter().any(|image| image == url) {
+            true => String::new(),
+            false if image[1].trim().is_empty() => format!("[image]({url})"),
+            false => format!("[{}]({url})", &image[1]),
+        }
+    });
+
+    EMPTY_LINK.replace_all(&stripped, "").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses an Atom entry with the HTML `body`.
+    fn entry(body: &str) -> Entry {
+        let atom = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Announcements</title>
+  <id>tag:example.com,2026:feed</id>
+  <updated>2026-10-17T08:00:00Z</updated>
+  <entry>
+    <title>Announcement</title>
+    <id>tag:example.com,2026:entry</id>
+    <updated>2026-10-17T08:00:00Z</updated>
+    <content type="html"><![CDATA[{body}]]></content>
+  </entry>
+</feed>"#
+        );
+
+        feed_rs::parser::parse(atom.as_bytes())
+            .expect("valid Atom feed")
+            .entries
+            .remove(0)
+    }
+
+    /// Extracts the media of an entry with the HTML `body`, and strips its images from the body.
+    fn announce(body: &str) -> (AnnouncementMedia, String) {
+        let media = extract_media(&entry(body), body);
+        let markdown = strip_images(&html2md::parse_html(body), &media.images);
+
+        (media, markdown)
+    }
+
+    #[test]
+    fn finds_images_and_files() {
+        let (media, _) = announce(
+            r#"<p><img src="https://example.com/a.png?x=1&amp;y=2"> <a href="https://example.com/files/12/download">Syllabus</a> <a href="https://example.com/b.JPG">photo</a> <a href="https://example.com/page">page</a></p>"#,
+        );
+
+        assert_eq!(
+            media.images,
+            vec![
+                "https://example.com/a.png?x=1&y=2",
+                "https://example.com/b.JPG"
+            ]
+        );
+        assert_eq!(
+            media.files,
+            vec![(
+                "Syllabus".to_string(),
+                "https://example.com/files/12/download".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn extracted_images_are_stripped() {
+        let (_, markdown) =
+            announce(r#"<p>Before <img src="https://example.com/a.png" alt="a"> after</p>"#);
+
+        assert!(!markdown.contains("a.png"));
+        assert!(markdown.contains("Before") && markdown.contains("after"));
+    }
+
+    #[test]
+    fn linked_images_leave_no_empty_link() {
+        let (_, markdown) = announce(
+            r#"<p>See <a href="https://example.com/page"><img src="https://example.com/a.png" alt="a"></a></p>"#,
+        );
+
+        assert!(!markdown.contains("[]"), "{markdown}");
+        assert!(!markdown.contains("a.png"), "{markdown}");
+    }
+
+    #[test]
+    fn images_that_cannot_be_shown_stay_as_links() {
+        let (media, markdown) = announce(
+            r#"<p><img src="/courses/1/files/2/preview" alt="diagram"> <img src="/x.png"></p>"#,
+        );
+
+        assert!(media.images.is_empty());
+        assert!(
+            markdown.contains("[diagram](/courses/1/files/2/preview)"),
+            "{markdown}"
+        );
+        assert!(markdown.contains("[image](/x.png)"), "{markdown}");
+    }
+}
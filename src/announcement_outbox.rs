@@ -0,0 +1,878 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use error_stack::{Report, ResultExt};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use twilight_http::{
+    error::{Error as HttpError, ErrorType},
+    Client,
+};
+use twilight_model::{
+    channel::{
+        message::{AllowedMentions, Embed},
+        thread::AutoArchiveDuration,
+    },
+    http::attachment::Attachment,
+    id::{
+        marker::{ChannelMarker, MessageMarker, RoleMarker, WebhookMarker},
+        Id,
+    },
+};
+
+use crate::error::{is_not_found, RssError};
+
+/// How long to wait before retrying an announcement after its first failed attempt. The wait
+/// doubles with each failed attempt.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// The longest to wait before retrying an announcement.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// The longest to wait before checking for announcements to deliver, in case a wake up is missed.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The most characters Discord allows in the name of a thread.
+const MAX_THREAD_NAME_LENGTH: usize = 100;
+
+/// The name of the webhooks the bot creates to post announcements with.
+const WEBHOOK_NAME: &str = "Announcements";
+
+/// The most characters Discord allows in the name a webhook posts with.
+const MAX_USERNAME_LENGTH: usize = 80;
+
+/// Posted in the thread of an announcement when the announcement is edited.
+const EDITED_NOTICE: &str = "The announcement above has been edited.";
+
+/// Lets the sender know that there are new announcements to deliver.
+static OUTBOX_CHANGED: tokio::sync::Notify = tokio::sync::Notify::const_new();
+
+/// The message an announcement is posted, or edited, with.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OutboxMessage {
+    /// The content of the message.
+    pub content: String,
+    /// The embeds of the message.
+    pub embeds: Vec<Embed>,
+    /// The files attached to the message.
+    #[serde(default)]
+    pub files: Vec<OutboxFile>,
+    /// The roles the message may ping. Nobody else is ever pinged.
+    #[serde(default)]
+    pub roles: Vec<Id<RoleMarker>>,
+    /// The thread to open on the message once it is posted, if any.
+    #[serde(default)]
+    pub thread: Option<OutboxThread>,
+    /// How the message appears when it is posted through a webhook, if it is not posted by the bot
+    /// itself.
+    #[serde(default)]
+    pub webhook: Option<OutboxWebhook>,
+}
+
+/// The name and avatar an announcement is posted through a webhook with.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OutboxWebhook {
+    /// The name to post with.
+    pub username: String,
+    /// The URL of the avatar to post with, if not the avatar of the webhook.
+    pub avatar_url: Option<String>,
+}
+
+/// A discussion thread opened on a posted announcement.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OutboxThread {
+    /// The name of the thread.
+    pub name: String,
+    /// How many minutes the thread stays open without activity.
+    pub auto_archive_duration: u16,
+}
+
+/// A text file attached to an announcement.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OutboxFile {
+    /// The name of the file.
+    pub filename: String,
+    /// The contents of the file.
+    pub content: String,
+}
+
+impl OutboxMessage {
+    /// The files of the message, in the form they are uploaded to Discord.
+    fn attachments(&self) -> Vec<Attachment> {
+        self.files
+            .iter()
+            .zip(0..)
+            .map(|(file, id)| {
+                Attachment::from_bytes(file.filename.clone(), file.content.clone().into_bytes(), id)
+            })
+            .collect()
+    }
+}
+
+/// What to do with an announcement on Discord.
+#[derive(Debug, Clone, Copy)]
+pub enum Delivery {
+    /// Post a new announcement to the channel.
+    Create { channel_id: Id<ChannelMarker> },
+    /// Edit an announcement that has already been posted, noting the edit in its thread if it has
+    /// one. Announcements posted through a webhook are edited through the same webhook.
+    Edit {
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+        thread_id: Option<Id<ChannelMarker>>,
+        webhook_id: Option<Id<WebhookMarker>>,
+    },
+}
+
+/// An announcement that was posted to Discord.
+#[derive(Debug)]
+struct Posted {
+    message_id: Id<MessageMarker>,
+    /// The thread opened on the announcement, if one was.
+    thread_id: Option<Id<ChannelMarker>>,
+    /// The webhook the announcement was posted through, if it was.
+    webhook_id: Option<Id<WebhookMarker>>,
+}
+
+/// Why delivering an announcement failed.
+struct DeliveryFailure {
+    /// Whether delivering it may succeed later, such as when Discord is having an outage.
+    retryable: bool,
+    /// Whether no announcement can be delivered until the bot is fixed, such as when its token has
+    /// been revoked.
+    fatal: bool,
+    /// What went wrong.
+    report: Report<RssError>,
+}
+
+/// Queues an announcement of a feed entry to be delivered to Discord.
+///
+/// The `connection` may be a transaction, so that the announcement is only queued along with the
+/// entry being recorded as seen. The sender is woken up by [`wake_outbox`].
+pub async fn enqueue_announcement(
+    feed_id: &str,
+    entry_id: &str,
+    delivery: Delivery,
+    message: &OutboxMessage,
+    connection: &mut SqliteConnection,
+) -> Result<(), Report<RssError>> {
+    let (kind, channel_id, message_id, thread_id, webhook_id) = match delivery {
+        Delivery::Create { channel_id } => ("create", channel_id, None, None, None),
+        Delivery::Edit {
+            channel_id,
+            message_id,
+            thread_id,
+            webhook_id,
+        } => ("edit", channel_id, Some(message_id), thread_id, webhook_id),
+    };
+    let channel_id = channel_id.to_string();
+    let message_id = message_id.map(|message_id| message_id.to_string());
+    let thread_id = thread_id.map(|thread_id| thread_id.to_string());
+    let webhook_id = webhook_id.map(|webhook_id| webhook_id.to_string());
+    let payload = serde_json::to_string(message).change_context(RssError::Database)?;
+    let current_time = Utc::now().timestamp_millis();
+
+    sqlx::query!(
+        r#"
+INSERT INTO announcement_outbox (feed_id, entry_id, kind, channel_id, message_id, thread_id, webhook_id, payload, next_attempt_time)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+	"#,
+        feed_id,
+        entry_id,
+        kind,
+        channel_id,
+        message_id,
+        thread_id,
+        webhook_id,
+        payload,
+        current_time
+    )
+    .execute(&mut *connection)
+    .await
+    .change_context(RssError::Database)?;
+
+    Ok(())
+}
+
+/// Replaces the message of an announcement of the entry that has not been posted yet, so that an
+/// entry edited before it was announced is posted as it is now.
+///
+/// Returns `false` if there was no such announcement.
+pub async fn replace_pending_announcement(
+    feed_id: &str,
+    entry_id: &str,
+    message: &OutboxMessage,
+    connection: &mut SqliteConnection,
+) -> Result<bool, Report<RssError>> {
+    let payload = serde_json::to_string(message).change_context(RssError::Database)?;
+
+    let result = sqlx::query!(
+        r#"
+UPDATE announcement_outbox
+SET payload = ?
+WHERE feed_id = ? AND entry_id = ? AND kind = 'create' AND status = 'pending'
+	"#,
+        payload,
+        feed_id,
+        entry_id
+    )
+    .execute(&mut *connection)
+    .await
+    .change_context(RssError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Lets the sender know that announcements were queued.
+pub fn wake_outbox() {
+    OUTBOX_CHANGED.notify_one();
+}
+
+/// Delivers the queued announcements to Discord, oldest first, for as long as the bot runs.
+///
+/// Announcements that fail because Discord is unreachable, overloaded or rate limiting are retried
+/// with an exponentially increasing delay. An announcement is only removed from the queue once
+/// Discord confirms it, so none are lost if the bot stops in the meantime. Failures no announcement
+/// can be delivered past, such as an invalid token, are returned.
+pub async fn deliver_announcements(
+    pool: SqlitePool,
+    client: Arc<Client>,
+) -> Result<(), Report<RssError>> {
+    loop {
+        let current_time = Utc::now().timestamp_millis();
+
+        let due = sqlx::query!(
+            r#"
+SELECT id, feed_id, entry_id, kind, channel_id, message_id, thread_id, webhook_id, payload, attempts
+FROM announcement_outbox
+WHERE status = 'pending' AND next_attempt_time <= ?
+ORDER BY id
+	"#,
+            current_time
+        )
+        .fetch_all(&pool)
+        .await
+        .change_context(RssError::Database)?;
+
+        for row in due {
+            let channel_id = u64::try_from(row.channel_id).ok().and_then(Id::new_checked);
+            let message_id = row
+                .message_id
+                .and_then(|message_id| u64::try_from(message_id).ok())
+                .and_then(Id::new_checked);
+            let thread_id = row
+                .thread_id
+                .and_then(|thread_id| u64::try_from(thread_id).ok())
+                .and_then(Id::new_checked);
+            let webhook_id = row
+                .webhook_id
+                .and_then(|webhook_id| u64::try_from(webhook_id).ok())
+                .and_then(Id::new_checked);
+            let delivery = match (row.kind.as_str(), channel_id, message_id) {
+                ("create", Some(channel_id), _) => Some(Delivery::Create { channel_id }),
+                ("edit", Some(channel_id), Some(message_id)) => Some(Delivery::Edit {
+                    channel_id,
+                    message_id,
+                    thread_id,
+                    webhook_id,
+                }),
+                _ => None,
+            };
+            let message = serde_json::from_str::<OutboxMessage>(&row.payload).ok();
+
+            let result = match delivery.zip(message) {
+                Some((delivery, message)) => deliver(delivery, &message, &client, &pool).await,
+                None => Err(DeliveryFailure {
+                    retryable: false,
+                    fatal: false,
+                    report: Report::new(RssError::Database)
+                        .attach(format!("Invalid queued announcement {}", row.id)),
+                }),
+            };
+
+            let mut transaction = pool.begin().await.change_context(RssError::Database)?;
+
+            match result {
+                Ok(posted) => {
+                    // remember the message of the announcement, so that it can be edited later
+                    if let Some(posted) = posted {
+                        let posted_message_id = posted.message_id.to_string();
+                        let thread_id = posted.thread_id.map(|thread_id| thread_id.to_string());
+                        let webhook_id = posted.webhook_id.map(|webhook_id| webhook_id.to_string());
+                        sqlx::query!(
+                            r#"
+UPDATE announcement_entry
+SET channel_id = ?, message_id = ?, thread_id = ?, webhook_id = ?
+WHERE feed_id = ? AND entry_id = ?
+	"#,
+                            row.channel_id,
+                            posted_message_id,
+                            thread_id,
+                            webhook_id,
+                            row.feed_id,
+                            row.entry_id
+                        )
+                        .execute(&mut *transaction)
+                        .await
+                        .change_context(RssError::Database)?;
+                    }
+
+                    // delivered announcements are not needed any more, so don't let them pile up
+                    sqlx::query!(
+                        r#"
+DELETE FROM announcement_outbox
+WHERE id = ?
+	"#,
+                        row.id
+                    )
+                    .execute(&mut *transaction)
+                    .await
+                    .change_context(RssError::Database)?;
+
+                    log::debug!("Delivered announcement of entry {}", row.entry_id);
+                }
+                // the announcement is left queued, to be delivered once the bot is fixed
+                Err(failure) if failure.fatal => {
+                    return Err(failure.report).attach(format!(
+                        "Failed to deliver announcement of entry {}",
+                        row.entry_id
+                    ));
+                }
+                Err(failure) => {
+                    let attempts = row.attempts + 1;
+                    let retry_delay = INITIAL_RETRY_DELAY
+                        .saturating_mul(2u32.saturating_pow((attempts - 1).clamp(0, 31) as u32))
+                        .min(MAX_RETRY_DELAY);
+                    let next_attempt_time = current_time + retry_delay.as_millis() as i64;
+                    let status = match failure.retryable {
+                        true => "pending",
+                        false => "failed",
+                    };
+                    let last_error = format!("{:?}", failure.report);
+
+                    match failure.retryable {
+                        true => log::warn!(
+                            "Failed to deliver announcement of entry {} ({attempts} attempts), retrying in {} seconds: {last_error}",
+                            row.entry_id,
+                            retry_delay.as_secs()
+                        ),
+                        false => log::error!(
+                            "Failed to deliver announcement of entry {}, giving up: {last_error}",
+                            row.entry_id
+                        ),
+                    }
+
+                    sqlx::query!(
+                        r#"
+UPDATE announcement_outbox
+SET status = ?, attempts = ?, next_attempt_time = ?, last_error = ?
+WHERE id = ?
+	"#,
+                        status,
+                        attempts,
+                        next_attempt_time,
+                        last_error,
+                        row.id
+                    )
+                    .execute(&mut *transaction)
+                    .await
+                    .change_context(RssError::Database)?;
+                }
+            }
+
+            transaction
+                .commit()
+                .await
+                .change_context(RssError::Database)?;
+        }
+
+        // sleep until the next announcement is due, or new ones are queued
+        let next_attempt_time = sqlx::query!(
+            r#"
+SELECT MIN(next_attempt_time) AS "next_attempt_time: i64"
+FROM announcement_outbox
+WHERE status = 'pending'
+	"#
+        )
+        .fetch_one(&pool)
+        .await
+        .change_context(RssError::Database)?
+        .next_attempt_time;
+
+        let wait = next_attempt_time
+            .map(|next_attempt_time| {
+                let millis = next_attempt_time - Utc::now().timestamp_millis();
+                Duration::from_millis(millis.max(0) as u64)
+            })
+            .unwrap_or(POLL_INTERVAL)
+            .min(POLL_INTERVAL);
+
+        tokio::select! {
+            _ = OUTBOX_CHANGED.notified() => {}
+            _ = tokio::time::sleep(wait) => {}
+        }
+    }
+}
+
+/// Posts or edits a single announcement, returning the message that was posted.
+///
+/// Opening the thread of an announcement, or noting an edit in it, is not retried if it fails, as
+/// the announcement itself has already been delivered.
+async fn deliver(
+    delivery: Delivery,
+    message: &OutboxMessage,
+    client: &Client,
+    pool: &SqlitePool,
+) -> Result<Option<Posted>, DeliveryFailure> {
+    // messages that Discord would never accept are not worth retrying
+    let invalid = |report: Report<RssError>| DeliveryFailure {
+        retryable: false,
+        fatal: false,
+        report,
+    };
+
+    let attachments = message.attachments();
+    let allowed_mentions = AllowedMentions {
+        roles: message.roles.clone(),
+        ..Default::default()
+    };
+
+    match delivery {
+        Delivery::Create { channel_id } => {
+            let (response, webhook_id) = match &message.webhook {
+                Some(webhook) => {
+                    let (webhook_id, token) = channel_webhook(channel_id, client, pool).await?;
+                    let username = webhook
+                        .username
+                        .chars()
+                        .take(MAX_USERNAME_LENGTH)
+                        .collect::<String>();
+
+                    let mut request = client
+                        .execute_webhook(webhook_id, &token)
+                        .allowed_mentions(Some(&allowed_mentions))
+                        .username(&username)
+                        .change_context(RssError::Post)
+                        .map_err(invalid)?
+                        .content(&message.content)
+                        .change_context(RssError::Post)
+                        .map_err(invalid)?
+                        .embeds(&message.embeds)
+                        .change_context(RssError::Post)
+                        .map_err(invalid)?
+                        .attachments(&attachments)
+                        .change_context(RssError::Post)
+                        .map_err(invalid)?;
+                    if let Some(avatar_url) = &webhook.avatar_url {
+                        request = request.avatar_url(avatar_url);
+                    }
+
+                    match request.wait().await {
+                        Ok(response) => (response, Some(webhook_id)),
+                        // someone deleted the webhook, so a new one is made on the next attempt
+                        Err(error) if is_not_found(&error) => {
+                            forget_webhook(webhook_id, pool).await.map_err(|report| {
+                                DeliveryFailure {
+                                    retryable: true,
+                                    fatal: false,
+                                    report,
+                                }
+                            })?;
+
+                            return Err(DeliveryFailure {
+                                retryable: true,
+                                fatal: false,
+                                report: Report::new(error)
+                                    .change_context(RssError::Webhook)
+                                    .attach("The webhook of the channel was deleted"),
+                            });
+                        }
+                        Err(error) => return Err(http_failure(error, RssError::Post)),
+                    }
+                }
+                None => {
+                    let response = client
+                        .create_message(channel_id)
+                        .allowed_mentions(Some(&allowed_mentions))
+                        .content(&message.content)
+                        .change_context(RssError::Post)
+                        .map_err(invalid)?
+                        .embeds(&message.embeds)
+                        .change_context(RssError::Post)
+                        .map_err(invalid)?
+                        .attachments(&attachments)
+                        .change_context(RssError::Post)
+                        .map_err(invalid)?
+                        .await
+                        .map_err(|error| http_failure(error, RssError::Post))?;
+
+                    (response, None)
+                }
+            };
+
+            // the announcement was posted, even if its message could not be read
+            let message_id = match response.model().await {
+                Ok(posted) => posted.id,
+                Err(error) => {
+                    log::warn!("Posted an announcement, but failed to read its message: {error}");
+                    return Ok(None);
+                }
+            };
+
+            let thread_id = match &message.thread {
+                Some(thread) => open_thread(channel_id, message_id, thread, client).await,
+                None => None,
+            };
+
+            Ok(Some(Posted {
+                message_id,
+                thread_id,
+                webhook_id,
+            }))
+        }
+        Delivery::Edit {
+            channel_id,
+            message_id,
+            thread_id,
+            webhook_id,
+        } => {
+            match webhook_id {
+                Some(webhook_id) => {
+                    let token = webhook_token(webhook_id, pool)
+                        .await
+                        .map_err(|report| DeliveryFailure {
+                            retryable: true,
+                            fatal: false,
+                            report,
+                        })?
+                        .ok_or_else(|| {
+                            invalid(Report::new(RssError::Webhook).attach(
+                                "The webhook the announcement was posted with has been deleted",
+                            ))
+                        })?;
+
+                    client
+                        .update_webhook_message(webhook_id, &token, message_id)
+                        .allowed_mentions(Some(&allowed_mentions))
+                        .embeds(Some(&message.embeds))
+                        .change_context(RssError::Edit)
+                        .map_err(invalid)?
+                        // replaces the attachments the announcement had before
+                        .attachments(&attachments)
+                        .change_context(RssError::Edit)
+                        .map_err(invalid)?
+                        .await
+                        .map_err(|error| http_failure(error, RssError::Edit))?;
+                }
+                None => {
+                    client
+                        .update_message(channel_id, message_id)
+                        .allowed_mentions(Some(&allowed_mentions))
+                        .embeds(Some(&message.embeds))
+                        .change_context(RssError::Edit)
+                        .map_err(invalid)?
+                        // replaces the attachments the announcement had before
+                        .attachments(&attachments)
+                        .change_context(RssError::Edit)
+                        .map_err(invalid)?
+                        .await
+                        .map_err(|error| http_failure(error, RssError::Edit))?;
+                }
+            }
+
+            if let Some(thread_id) = thread_id {
+                let result = match client
+                    .create_message(thread_id)
+                    .allowed_mentions(Some(&AllowedMentions::default()))
+                    .content(EDITED_NOTICE)
+                {
+                    Ok(request) => request.await.map(|_| ()).map_err(|error| error.to_string()),
+                    Err(error) => Err(error.to_string()),
+                };
+
+                if let Err(error) = result {
+                    log::warn!(
+                        "Edited an announcement, but failed to note it in its thread: {error}"
+                    );
+                }
+            }
+
+            Ok(None)
+        }
+    }
+}
+
+/// Opens a discussion thread on a posted announcement, returning the ID of the thread.
+async fn open_thread(
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    thread: &OutboxThread,
+    client: &Client,
+) -> Option<Id<ChannelMarker>> {
+    // threads must have a name
+    let name = match thread.name.trim() {
+        "" => "Discussion".to_string(),
+        name => name.chars().take(MAX_THREAD_NAME_LENGTH).collect(),
+    };
+
+    let result = match client.create_thread_from_message(channel_id, message_id, &name) {
+        Ok(request) => match request
+            .auto_archive_duration(AutoArchiveDuration::from(thread.auto_archive_duration))
+            .await
+        {
+            Ok(response) => response.model().await.map_err(|error| error.to_string()),
+            Err(error) => Err(error.to_string()),
+        },
+        Err(error) => Err(error.to_string()),
+    };
+
+    match result {
+        Ok(thread) => Some(thread.id),
+        Err(error) => {
+            log::warn!("Posted an announcement, but failed to open its thread: {error}");
+            None
+        }
+    }
+}
+
+/// Finds the webhook the bot posts announcements with in a channel, creating it the first time it is
+/// needed. Returns the ID and token of the webhook.
+async fn channel_webhook(
+    channel_id: Id<ChannelMarker>,
+    client: &Client,
+    pool: &SqlitePool,
+) -> Result<(Id<WebhookMarker>, String), DeliveryFailure> {
+    let database = |report: Report<RssError>| DeliveryFailure {
+        retryable: true,
+        fatal: false,
+        report,
+    };
+    let channel_id_str = channel_id.to_string();
+
+    let stored = sqlx::query!(
+        r#"
+SELECT webhook_id, token
+FROM announcement_webhook
+WHERE channel_id = ?
+	"#,
+        channel_id_str
+    )
+    .fetch_optional(pool)
+    .await
+    .change_context(RssError::Database)
+    .map_err(database)?;
+
+    if let Some(stored) = stored {
+        if let Some(webhook_id) = u64::try_from(stored.webhook_id)
+            .ok()
+            .and_then(Id::new_checked)
+        {
+            return Ok((webhook_id, stored.token));
+        }
+    }
+
+    // reuse a webhook the bot made before, rather than making another
+    let current_user = client
+        .current_user()
+        .await
+        .map_err(|error| http_failure(error, RssError::Webhook))?
+        .model()
+        .await
+        .change_context(RssError::Webhook)
+        .map_err(database)?;
+    let existing = client
+        .channel_webhooks(channel_id)
+        .await
+        .map_err(|error| http_failure(error, RssError::Webhook))?
+        .models()
+        .await
+        .change_context(RssError::Webhook)
+        .map_err(database)?
+        .into_iter()
+        .find(|webhook| {
+            webhook.name.as_deref() == Some(WEBHOOK_NAME)
+                && webhook.token.is_some()
+                && webhook.user.as_ref().map(|user| user.id) == Some(current_user.id)
+        });
+
+    let webhook = match existing {
+        Some(webhook) => webhook,
+        None => client
+            .create_webhook(channel_id, WEBHOOK_NAME)
+            .change_context(RssError::Webhook)
+            .map_err(|report| DeliveryFailure {
+                retryable: false,
+                fatal: false,
+                report,
+            })?
+            .await
+            .map_err(|error| http_failure(error, RssError::Webhook))?
+            .model()
+            .await
+            .change_context(RssError::Webhook)
+            .map_err(database)?,
+    };
+
+    let Some(token) = webhook.token else {
+        return Err(DeliveryFailure {
+            retryable: false,
+            fatal: false,
+            report: Report::new(RssError::Webhook)
+                .attach(format!("Webhook {} has no token to post with", webhook.id)),
+        });
+    };
+    let webhook_id = webhook.id.to_string();
+
+    sqlx::query!(
+        r#"
+INSERT INTO announcement_webhook (channel_id, webhook_id, token)
+VALUES (?, ?, ?)
+ON CONFLICT (channel_id) DO UPDATE SET
+	webhook_id = excluded.webhook_id,
+	token = excluded.token
+	"#,
+        channel_id_str,
+        webhook_id,
+        token
+    )
+    .execute(pool)
+    .await
+    .change_context(RssError::Database)
+    .map_err(database)?;
+
+    log::info!(
+        "Posting announcements in channel {channel_id} through webhook {}",
+        webhook.id
+    );
+
+    Ok((webhook.id, token))
+}
+
+/// Retrieves the token of a webhook the bot posts announcements with, if it is still known.
+async fn webhook_token(
+    webhook_id: Id<WebhookMarker>,
+    pool: &SqlitePool,
+) -> Result<Option<String>, Report<RssError>> {
+    let webhook_id = webhook_id.to_string();
+
+    let token = sqlx::query!(
+        r#"
+SELECT token
+FROM announcement_webhook
+WHERE webhook_id = ?
+	"#,
+        webhook_id
+    )
+    .fetch_optional(pool)
+    .await
+    .change_context(RssError::Database)?
+    .map(|row| row.token);
+
+    Ok(token)
+}
+
+/// Forgets about a webhook that no longer exists, so that a new one is made in its place.
+async fn forget_webhook(
+    webhook_id: Id<WebhookMarker>,
+    pool: &SqlitePool,
+) -> Result<(), Report<RssError>> {
+    let webhook_id = webhook_id.to_string();
+
+    sqlx::query!(
+        r#"
+DELETE FROM announcement_webhook
+WHERE webhook_id = ?
+	"#,
+        webhook_id
+    )
+    .execute(pool)
+    .await
+    .change_context(RssError::Database)?;
+
+    Ok(())
+}
+
+/// Decides whether a request that Discord failed may succeed if tried again.
+fn http_failure(error: HttpError, context: RssError) -> DeliveryFailure {
+    DeliveryFailure {
+        retryable: is_retryable(error.kind()),
+        // the token is invalid, so every other announcement would fail the same way
+        fatal: matches!(error.kind(), ErrorType::Unauthorized),
+        report: Report::new(error).change_context(context),
+    }
+}
+
+/// Whether a request that failed with the `kind` of error may succeed if tried again.
+fn is_retryable(kind: &ErrorType) -> bool {
+    match kind {
+        // Discord is overloaded, rate limiting, or unreachable
+        ErrorType::Response { status, .. } => status.get() == 429 || status.is_server_error(),
+        ErrorType::ServiceUnavailable { .. }
+        | ErrorType::RequestError
+        | ErrorType::RequestTimedOut
+        | ErrorType::RequestCanceled
+        | ErrorType::RatelimiterTicket
+        | ErrorType::ChunkingResponse => true,
+        // such as missing permissions, or the announcement having been deleted
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outages_are_retried() {
+        for kind in [
+            ErrorType::RequestError,
+            ErrorType::RequestTimedOut,
+            ErrorType::RequestCanceled,
+            ErrorType::RatelimiterTicket,
+            ErrorType::ChunkingResponse,
+        ] {
+            assert!(is_retryable(&kind), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_requests_are_not_retried() {
+        for kind in [
+            ErrorType::BuildingRequest,
+            ErrorType::Json,
+            ErrorType::Parsing { body: vec![] },
+            ErrorType::Unauthorized,
+        ] {
+            assert!(!is_retryable(&kind), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn queued_messages_survive_being_stored() {
+        let message = OutboxMessage {
+            content: "<@&1>".to_string(),
+            embeds: vec![],
+            files: vec![OutboxFile {
+                filename: "announcement.md".to_string(),
+                content: "body".to_string(),
+            }],
+            roles: vec![Id::new(1)],
+            thread: Some(OutboxThread {
+                name: "Exam".to_string(),
+                auto_archive_duration: 1440,
+            }),
+            webhook: None,
+        };
+
+        let stored = serde_json::to_string(&message).unwrap();
+        let restored = serde_json::from_str::<OutboxMessage>(&stored).unwrap();
+        assert_eq!(restored.roles, message.roles);
+        assert_eq!(restored.files[0].content, "body");
+        assert_eq!(restored.thread.unwrap().auto_archive_duration, 1440);
+
+        // messages queued before roles, threads and webhooks existed are still delivered
+        let old = serde_json::from_str::<OutboxMessage>(r#"{"content":"","embeds":[],"files":[]}"#)
+            .unwrap();
+        assert!(old.roles.is_empty() && old.thread.is_none() && old.webhook.is_none());
+    }
+}
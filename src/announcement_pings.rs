@@ -0,0 +1,262 @@
+use std::fmt::Display;
+
+use error_stack::{Report, ResultExt};
+use feed_rs::model::Entry;
+use regex::Regex;
+use sqlx::SqlitePool;
+use twilight_model::id::{marker::RoleMarker, Id};
+
+use crate::{
+    announcement_filters::{compile_pattern, EntryText, FilterKind},
+    error::RssError,
+};
+
+/// A rule pinging a role for the announcements that match it.
+#[derive(Debug, Clone)]
+pub struct PingRule {
+    /// Identifies the rule, so that it can be removed.
+    pub id: i64,
+    /// The feed the rule applies to, or `None` if it applies to every feed.
+    pub url: Option<String>,
+    pub kind: FilterKind,
+    pub pattern: String,
+    pub role_id: Id<RoleMarker>,
+}
+
+impl Display for PingRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} {} `{}` pings <@&{}>",
+            self.id,
+            self.kind.as_str(),
+            self.pattern.replace('`', "'"),
+            self.role_id
+        )?;
+
+        match &self.url {
+            Some(url) => write!(f, " in {url}"),
+            None => write!(f, " in every feed"),
+        }
+    }
+}
+
+/// The ping rules of a feed, ready to be applied to its entries.
+#[derive(Debug, Default)]
+pub struct FeedPings {
+    rules: Vec<(PingRule, Option<Regex>)>,
+}
+
+impl FeedPings {
+    /// Prepares the `rules` of a feed, compiling their regular expressions.
+    pub fn new(rules: Vec<PingRule>) -> Result<Self, Report<RssError>> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let regex = compile_pattern(rule.kind, &rule.pattern)
+                    .attach_with(|| format!("Invalid ping rule {rule}"))?;
+                Ok((rule, regex))
+            })
+            .collect::<Result<_, Report<RssError>>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Whether an entry matches any of the rules, making it urgent enough to be announced straight
+    /// away.
+    pub fn matches(&self, entry: &Entry) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+
+        let text = EntryText::new(entry);
+        self.rules
+            .iter()
+            .any(|(rule, regex)| text.matches(rule.kind, &rule.pattern, regex.as_ref()))
+    }
+
+    /// The roles to ping when announcing an entry, which are the roles of the rules it matches.
+    /// Each role is listed once.
+    ///
+    /// Feeds without any rules ping their own `role_id` instead, so that rules can narrow down who
+    /// is pinged.
+    pub fn roles(&self, role_id: Option<Id<RoleMarker>>, entry: &Entry) -> Vec<Id<RoleMarker>> {
+        if self.rules.is_empty() {
+            return role_id.into_iter().collect();
+        }
+
+        let mut roles = vec![];
+        let text = EntryText::new(entry);
+        for (rule, regex) in &self.rules {
+            if !roles.contains(&rule.role_id)
+                && text.matches(rule.kind, &rule.pattern, regex.as_ref())
+            {
+                roles.push(rule.role_id);
+            }
+        }
+
+        roles
+    }
+}
+
+/// Retrieves the ping rules that apply to the feed with the `url`, or every ping rule if no `url` is
+/// given, in the order they were added.
+pub async fn list_pings(
+    url: Option<&str>,
+    pool: &SqlitePool,
+) -> Result<Vec<PingRule>, Report<RssError>> {
+    sqlx::query!(
+        r#"
+SELECT id AS "id!: i64", url, kind, pattern, role_id
+FROM announcement_ping
+WHERE ?1 IS NULL OR url IS NULL OR url = ?1
+ORDER BY id
+	"#,
+        url
+    )
+    .fetch_all(pool)
+    .await
+    .change_context(RssError::Database)?
+    .into_iter()
+    .map(|row| {
+        Ok(PingRule {
+            id: row.id,
+            kind: row.kind.parse()?,
+            pattern: row.pattern,
+            role_id: u64::try_from(row.role_id)
+                .ok()
+                .and_then(Id::new_checked)
+                .ok_or(RssError::Database)
+                .attach_with(|| format!("Invalid role of ping rule #{}", row.id))?,
+            url: row.url,
+        })
+    })
+    .collect()
+}
+
+/// Retrieves the ping rules that apply to the feed with the `url`, ready to be applied to its
+/// entries.
+pub async fn feed_pings(url: &str, pool: &SqlitePool) -> Result<FeedPings, Report<RssError>> {
+    FeedPings::new(list_pings(Some(url), pool).await?)
+}
+
+/// Adds a ping rule, applying to the feed with the `url` or to every feed, returning the ID of the
+/// new rule.
+///
+/// Returns `None` if there is no feed with the `url`.
+pub async fn add_ping(
+    url: Option<&str>,
+    kind: FilterKind,
+    pattern: &str,
+    role_id: Id<RoleMarker>,
+    pool: &SqlitePool,
+) -> Result<Option<i64>, Report<RssError>> {
+    let kind = kind.as_str();
+    let role_id = role_id.to_string();
+
+    let id = sqlx::query!(
+        r#"
+INSERT INTO announcement_ping (url, kind, pattern, role_id)
+SELECT ?1, ?2, ?3, ?4
+WHERE ?1 IS NULL OR EXISTS (SELECT 1 FROM announcement_source WHERE url = ?1)
+RETURNING id AS "id!: i64"
+	"#,
+        url,
+        kind,
+        pattern,
+        role_id
+    )
+    .fetch_optional(pool)
+    .await
+    .change_context(RssError::Database)?
+    .map(|row| row.id);
+
+    Ok(id)
+}
+
+/// Removes the ping rule with the `id`.
+///
+/// Returns `false` if there was no such rule.
+pub async fn remove_ping(id: i64, pool: &SqlitePool) -> Result<bool, Report<RssError>> {
+    let result = sqlx::query!(
+        r#"
+DELETE FROM announcement_ping
+WHERE id = ?
+	"#,
+        id
+    )
+    .execute(pool)
+    .await
+    .change_context(RssError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses an Atom entry with the `title`.
+    fn entry(title: &str) -> Entry {
+        let atom = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Announcements</title>
+  <id>tag:example.com,2026:feed</id>
+  <updated>2026-10-17T08:00:00Z</updated>
+  <entry>
+    <title>{title}</title>
+    <id>tag:example.com,2026:entry</id>
+    <updated>2026-10-17T08:00:00Z</updated>
+  </entry>
+</feed>"#
+        );
+
+        feed_rs::parser::parse(atom.as_bytes())
+            .expect("valid Atom feed")
+            .entries
+            .remove(0)
+    }
+
+    fn rule(id: i64, kind: FilterKind, pattern: &str, role_id: u64) -> PingRule {
+        PingRule {
+            id,
+            url: None,
+            kind,
+            pattern: pattern.to_string(),
+            role_id: Id::new(role_id),
+        }
+    }
+
+    #[test]
+    fn feeds_without_rules_ping_their_role() {
+        let pings = FeedPings::new(vec![]).unwrap();
+        let entry = entry("Exam moved");
+
+        assert_eq!(pings.roles(Some(Id::new(1)), &entry), vec![Id::new(1)]);
+        assert!(pings.roles(None, &entry).is_empty());
+        assert!(!pings.matches(&entry));
+    }
+
+    #[test]
+    fn rules_ping_only_the_roles_they_match() {
+        let pings = FeedPings::new(vec![
+            rule(1, FilterKind::Keyword, "exam", 10),
+            rule(2, FilterKind::Regex, r"(?i)\bmoved\b", 20),
+            rule(3, FilterKind::Keyword, "exam", 10),
+            rule(4, FilterKind::Keyword, "homework", 30),
+        ])
+        .unwrap();
+
+        let exam = entry("Exam moved");
+        assert_eq!(
+            pings.roles(Some(Id::new(1)), &exam),
+            vec![Id::new(10), Id::new(20)]
+        );
+        assert!(pings.matches(&exam));
+
+        let other = entry("Welcome");
+        assert!(pings.roles(Some(Id::new(1)), &other).is_empty());
+        assert!(!pings.matches(&other));
+    }
+}
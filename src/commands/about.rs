@@ -0,0 +1,66 @@
+use error_stack::Report;
+use twilight_model::{
+    application::command::{Command, CommandType},
+    channel::message::{embed::EmbedField, Embed},
+};
+use twilight_util::builder::command::CommandBuilder;
+
+use crate::{config::ApplicationConfig, error::CommandError};
+
+use super::CommandResponse;
+
+pub const NAME: &str = "about";
+
+/// Whether the response is only shown to the user that used the command.
+pub const EPHEMERAL: bool = true;
+
+pub fn definition() -> Command {
+    CommandBuilder::new(
+        NAME,
+        "Shows information about the bot and how the starboards are configured",
+        CommandType::ChatInput,
+    )
+    .build()
+}
+
+/// Describes the running version of the bot, and the starboards it posts to.
+pub async fn run(config: &ApplicationConfig) -> Result<CommandResponse, Report<CommandError>> {
+    let fields = config
+        .starboards
+        .iter()
+        .map(|board| EmbedField {
+            inline: false,
+            name: board.name.clone(),
+            value: format!(
+                "{} reactions{} to be posted in <#{}>",
+                board.reaction_requirement,
+                match board.emojis.is_empty() {
+                    true => String::new(),
+                    false => format!(" of {}", board.emojis.join(" ")),
+                },
+                board.channel_id
+            ),
+        })
+        .collect();
+
+    Ok(CommandResponse {
+        content: String::new(),
+        embeds: vec![Embed {
+            author: None,
+            color: Some(15844367),
+            description: Some(env!("CARGO_PKG_DESCRIPTION").to_string()),
+            fields,
+            footer: None,
+            timestamp: None,
+            kind: "rich".to_string(),
+            image: None,
+            provider: None,
+            thumbnail: None,
+            title: Some(
+                concat!(env!("CARGO_PKG_NAME"), " v", env!("CARGO_PKG_VERSION")).to_string(),
+            ),
+            url: None,
+            video: None,
+        }],
+    })
+}
@@ -0,0 +1,772 @@
+use std::time::Duration;
+
+use error_stack::{Report, ResultExt};
+use feed_rs::model::Feed;
+use regex::Regex;
+use sqlx::SqlitePool;
+use twilight_model::{
+    application::{
+        command::{Command, CommandType},
+        interaction::application_command::{CommandData, CommandDataOption, CommandOptionValue},
+    },
+    channel::{message::Embed, thread::AutoArchiveDuration, ChannelType},
+    guild::Permissions,
+};
+use twilight_util::builder::command::{
+    BooleanBuilder, ChannelBuilder, CommandBuilder, IntegerBuilder, RoleBuilder, StringBuilder,
+    SubCommandBuilder, SubCommandGroupBuilder,
+};
+
+use crate::{
+    announcement_feeds::{add_feed, list_feeds, remove_feed, set_feed_enabled},
+    announcement_filters::{
+        add_filter, feed_filter, list_filters, remove_filter, FeedFilter, FilterKind,
+    },
+    announcement_pings::{add_ping, feed_pings, list_pings, remove_ping, FeedPings},
+    config::{AnnouncementFeed, ApplicationConfig, DigestSchedule, WebhookIdentity},
+    error::{CommandError, RssError},
+    markdown::{escape_mentions, join_lines},
+    rss_announcements::{
+        announcement_message, get_channel_announcements, reload_feeds, web_client, CacheValidators,
+        FeedResponse,
+    },
+};
+
+use super::{option, subcommand, CommandResponse};
+
+pub const NAME: &str = "feed";
+
+/// Whether the response is only shown to the user that used the command.
+pub const EPHEMERAL: bool = true;
+
+/// The shortest interval a feed may be checked at, in seconds.
+const MIN_CHECK_INTERVAL: i64 = 30;
+
+/// The most characters Discord allows in the name a webhook posts with.
+const MAX_WEBHOOK_NAME_LENGTH: u16 = 80;
+
+/// The most characters Discord allows in the description of an embed.
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+
+pub fn definition() -> Command {
+    let url_option = || StringBuilder::new("url", "The URL of the feed").required(true);
+    let kind_option = || {
+        StringBuilder::new("kind", "What the rule matches entries by")
+            .choices([
+                ("Keyword in the title or body", "keyword"),
+                ("Regular expression on the title or body", "regex"),
+                ("Name of an author", "author"),
+            ])
+            .required(true)
+    };
+    let pattern_option =
+        || StringBuilder::new("pattern", "The keyword, expression or name to match").required(true);
+    let id_option = || {
+        IntegerBuilder::new("id", "The number of the rule, as listed")
+            .min_value(1)
+            .required(true)
+    };
+
+    CommandBuilder::new(
+        NAME,
+        "Manage the announcement feeds",
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::MANAGE_GUILD)
+    .dm_permission(false)
+    .option(
+        SubCommandBuilder::new("add", "Announces the entries of a feed in a channel")
+            .option(url_option())
+            .option(
+                ChannelBuilder::new("channel", "The channel to post announcements to")
+                    .channel_types([ChannelType::GuildText, ChannelType::GuildAnnouncement])
+                    .required(true),
+            )
+            .option(RoleBuilder::new(
+                "role",
+                "The role to ping when announcements are made, unless ping rules apply to the feed",
+            ))
+            .option(StringBuilder::new(
+                "label",
+                "A name to recognise the feed by, such as its course",
+            ))
+            .option(
+                IntegerBuilder::new(
+                    "interval",
+                    "The amount of seconds between checks of the feed",
+                )
+                .min_value(MIN_CHECK_INTERVAL),
+            )
+            .option(
+                IntegerBuilder::new(
+                    "thread",
+                    "Opens a thread on each announcement, archived after this long without activity",
+                )
+                .choices([
+                    ("1 hour", 60),
+                    ("1 day", 1440),
+                    ("3 days", 4320),
+                    ("1 week", 10080),
+                ]),
+            )
+            .option(BooleanBuilder::new(
+                "webhook",
+                "Posts announcements through a webhook, under the name of the feed",
+            ))
+            .option(
+                StringBuilder::new(
+                    "webhook_name",
+                    "The name to post announcements through a webhook with",
+                )
+                .max_length(MAX_WEBHOOK_NAME_LENGTH),
+            )
+            .option(StringBuilder::new(
+                "webhook_avatar",
+                "The URL of the avatar to post announcements through a webhook with",
+            ))
+            .option(StringBuilder::new(
+                "digest",
+                "Gathers announcements into a digest, posted `hourly` or `daily HH:MM`",
+            )),
+    )
+    .option(
+        SubCommandBuilder::new("remove", "Stops announcing the entries of a feed")
+            .option(url_option()),
+    )
+    .option(
+        SubCommandBuilder::new("pause", "Stops checking a feed until it is resumed")
+            .option(url_option()),
+    )
+    .option(
+        SubCommandBuilder::new("resume", "Starts checking a paused feed again")
+            .option(url_option()),
+    )
+    .option(SubCommandBuilder::new(
+        "list",
+        "Lists the announcement feeds",
+    ))
+    .option(
+        SubCommandBuilder::new(
+            "test",
+            "Fetches a feed, previewing its newest entry without announcing it",
+        )
+        .option(url_option()),
+    )
+    .option(
+        SubCommandBuilder::new(
+            "dry-run",
+            "Fetches a feed, showing which of its entries its filter rules let through",
+        )
+        .option(url_option()),
+    )
+    .option(
+        SubCommandGroupBuilder::new("filter", "Decides which entries of a feed are announced")
+            .subcommands([
+                SubCommandBuilder::new("add", "Adds a filter rule to a feed")
+                    .option(url_option())
+                    .option(
+                        StringBuilder::new("action", "What to do with the entries it matches")
+                            .choices([
+                                ("Only announce matching entries", "include"),
+                                ("Never announce matching entries", "exclude"),
+                            ])
+                            .required(true),
+                    )
+                    .option(kind_option())
+                    .option(pattern_option()),
+                SubCommandBuilder::new("remove", "Removes a filter rule").option(id_option()),
+                SubCommandBuilder::new("list", "Lists the filter rules of a feed")
+                    .option(url_option()),
+            ]),
+    )
+    .option(
+        SubCommandGroupBuilder::new("ping", "Pings roles for the announcements matching a rule")
+            .subcommands([
+                SubCommandBuilder::new("add", "Pings a role for the announcements matching a rule")
+                    .option(kind_option())
+                    .option(pattern_option())
+                    .option(RoleBuilder::new("role", "The role to ping").required(true))
+                    .option(StringBuilder::new(
+                        "url",
+                        "The URL of the feed the rule applies to, if not every feed",
+                    )),
+                SubCommandBuilder::new("remove", "Removes a ping rule").option(id_option()),
+                SubCommandBuilder::new("list", "Lists the ping rules").option(StringBuilder::new(
+                    "url",
+                    "Only list the rules applying to this feed",
+                )),
+            ]),
+    )
+    .build()
+}
+
+/// Manages the announcement feeds.
+pub async fn run(
+    command: &CommandData,
+    pool: &SqlitePool,
+    config: &ApplicationConfig,
+) -> Result<CommandResponse, Report<CommandError>> {
+    let (name, options) = subcommand(&command.options)?;
+
+    let url = match option(options, "url") {
+        Some(CommandOptionValue::String(url)) => Some(url.trim()),
+        _ => None,
+    };
+
+    match (name, url) {
+        ("add", Some(url)) => {
+            let Some(CommandOptionValue::Channel(channel_id)) = option(options, "channel") else {
+                return Err(Report::new(CommandError::InvalidOptions))
+                    .attach("Expected a channel to post announcements to");
+            };
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                return Ok(CommandResponse::text(format!(
+                    "`{url}` is not a URL, it should start with `https://`."
+                )));
+            }
+
+            let webhook_name = match option(options, "webhook_name") {
+                Some(CommandOptionValue::String(name)) => Some(name.trim().to_string()),
+                _ => None,
+            };
+            let webhook_avatar_url = match option(options, "webhook_avatar") {
+                Some(CommandOptionValue::String(url)) => Some(url.trim().to_string()),
+                _ => None,
+            };
+            // giving a name or avatar implies posting through a webhook
+            let webhook = match option(options, "webhook") {
+                Some(CommandOptionValue::Boolean(webhook)) => *webhook,
+                _ => webhook_name.is_some() || webhook_avatar_url.is_some(),
+            };
+
+            // Discord refuses webhook names that impersonate it
+            if let Some(name) = webhook_name.as_ref().filter(|name| {
+                let name = name.to_lowercase();
+                name.is_empty() || name.contains("discord") || name.contains("clyde")
+            }) {
+                return Ok(CommandResponse::text(format!(
+                    "`{name}` cannot be used as the name of a webhook."
+                )));
+            }
+            if let Some(url) = webhook_avatar_url
+                .as_ref()
+                .filter(|url| !url.starts_with("https://") && !url.starts_with("http://"))
+            {
+                return Ok(CommandResponse::text(format!(
+                    "`{url}` is not a URL, it should start with `https://`."
+                )));
+            }
+
+            let digest = match option(options, "digest") {
+                Some(CommandOptionValue::String(schedule)) => {
+                    match DigestSchedule::parse(schedule) {
+                        Some(digest) => Some(digest),
+                        None => {
+                            return Ok(CommandResponse::text(format!(
+                                "`{schedule}` is not a digest schedule, it should be `hourly` or `daily HH:MM`, such as `daily 08:00`."
+                            )))
+                        }
+                    }
+                }
+                _ => None,
+            };
+
+            let feed = AnnouncementFeed {
+                url: url.to_string(),
+                channel_id: *channel_id,
+                role_id: match option(options, "role") {
+                    Some(CommandOptionValue::Role(role_id)) => Some(*role_id),
+                    _ => None,
+                },
+                label: match option(options, "label") {
+                    Some(CommandOptionValue::String(label)) => Some(label.clone()),
+                    _ => None,
+                },
+                check_interval: match option(options, "interval") {
+                    Some(CommandOptionValue::Integer(secs)) => {
+                        Some(Duration::from_secs((*secs).max(MIN_CHECK_INTERVAL) as u64))
+                    }
+                    _ => None,
+                },
+                thread_archive_duration: match option(options, "thread") {
+                    Some(CommandOptionValue::Integer(minutes)) => {
+                        u16::try_from(*minutes).ok().map(AutoArchiveDuration::from)
+                    }
+                    _ => None,
+                },
+                webhook: webhook.then_some(WebhookIdentity {
+                    name: webhook_name,
+                    avatar_url: webhook_avatar_url,
+                }),
+                digest,
+            };
+
+            let added = add_feed(&feed, pool)
+                .await
+                .change_context(CommandError::Database)?;
+            if !added {
+                return Ok(CommandResponse::text(format!(
+                    "{url} is already announced in another channel. Remove it first to announce it in <#{channel_id}> instead."
+                )));
+            }
+            reload_feeds();
+
+            let threads = match feed.thread_archive_duration {
+                Some(_) => ", each with a thread to discuss it in",
+                None => "",
+            };
+            let digest = match feed.digest {
+                Some(digest) => format!(
+                    " New entries are gathered into a digest posted {digest} ({}), except those matching a ping rule.",
+                    config.digest_timezone
+                ),
+                None => String::new(),
+            };
+            let webhook = match feed.webhook {
+                Some(_) => " Announcements are posted through a webhook, which the bot needs the Manage Webhooks permission for.",
+                None => "",
+            };
+
+            Ok(CommandResponse::text(format!(
+                "Announcing the entries of {url} in <#{channel_id}>{threads}. Entries already in the feed are not announced.{digest}{webhook}"
+            )))
+        }
+        ("remove", Some(url)) => {
+            let removed = remove_feed(url, pool)
+                .await
+                .change_context(CommandError::Database)?;
+            reload_feeds();
+
+            Ok(CommandResponse::text(match removed {
+                true => format!("No longer announcing the entries of {url}."),
+                false => format!("There is no feed with the URL {url}."),
+            }))
+        }
+        ("pause" | "resume", Some(url)) => {
+            let enabled = name == "resume";
+            let updated = set_feed_enabled(url, enabled, pool)
+                .await
+                .change_context(CommandError::Database)?;
+            reload_feeds();
+
+            Ok(CommandResponse::text(match (updated, enabled) {
+                (true, true) => format!("Resumed checking {url}."),
+                (true, false) => format!("Paused checking {url}."),
+                (false, _) => format!("There is no feed with the URL {url}."),
+            }))
+        }
+        ("list", _) => list(pool, config).await,
+        ("test", Some(url)) => test(url).await,
+        ("dry-run", Some(url)) => dry_run(url, pool).await,
+        ("filter", _) => filter(options, pool).await,
+        ("ping", _) => ping(options, pool).await,
+        _ => Err(Report::new(CommandError::InvalidOptions))
+            .attach(format!("Unknown subcommand {name}, or missing its URL")),
+    }
+}
+
+/// Lists every announcement feed, and where it is announced.
+async fn list(
+    pool: &SqlitePool,
+    config: &ApplicationConfig,
+) -> Result<CommandResponse, Report<CommandError>> {
+    let feeds = list_feeds(pool)
+        .await
+        .change_context(CommandError::Database)?;
+
+    if feeds.is_empty() {
+        return Ok(CommandResponse::text(
+            "There are no announcement feeds. Add one with `/feed add`.",
+        ));
+    }
+
+    let mut description = String::new();
+    for (i, stored) in feeds.iter().enumerate() {
+        let feed = &stored.feed;
+        let check_interval = feed
+            .check_interval
+            .unwrap_or(config.announcement_check_interval);
+
+        let mut line = format!(
+            "[{}]({}) in <#{}>",
+            feed.label.as_deref().unwrap_or(&feed.url),
+            feed.url,
+            feed.channel_id
+        );
+        if let Some(role_id) = feed.role_id {
+            line.push_str(&format!(", pinging <@&{role_id}>"));
+        }
+        line.push_str(&format!(", every {} seconds", check_interval.as_secs()));
+        if feed.thread_archive_duration.is_some() {
+            line.push_str(", with threads");
+        }
+        if let Some(digest) = feed.digest {
+            line.push_str(&format!(", in a digest posted {digest}"));
+        }
+        if let Some(webhook) = &feed.webhook {
+            match &webhook.name {
+                Some(name) => line.push_str(&format!(", posted as {name}")),
+                None => line.push_str(", posted through a webhook"),
+            }
+        }
+        if !stored.enabled {
+            line.push_str(" (paused)");
+        }
+
+        // leave room to mention the feeds that did not fit
+        if description.len() + line.len() + 32 > MAX_DESCRIPTION_LENGTH {
+            description.push_str(&format!("…and {} more", feeds.len() - i));
+            break;
+        }
+        description.push_str(&line);
+        description.push('\n');
+    }
+
+    Ok(CommandResponse {
+        content: String::new(),
+        embeds: vec![Embed {
+            author: None,
+            color: Some(15844367),
+            description: Some(description),
+            fields: vec![],
+            footer: None,
+            timestamp: None,
+            kind: "rich".to_string(),
+            image: None,
+            provider: None,
+            thumbnail: None,
+            title: Some("Announcement feeds".to_string()),
+            url: None,
+            video: None,
+        }],
+    })
+}
+
+/// Fetches a feed, or explains why it could not be read.
+async fn fetch(url: &str) -> Result<Box<Feed>, CommandResponse> {
+    let response =
+        get_channel_announcements(&web_client(), &url.to_string(), &CacheValidators::default())
+            .await;
+
+    match response {
+        Ok(FeedResponse::Modified { feed, .. }) => Ok(feed),
+        Ok(FeedResponse::NotModified) => Err(CommandResponse::text(format!(
+            "{url} replied that it has not changed."
+        ))),
+        Err(report) => Err(CommandResponse::text(format!(
+            "Failed to read {url}: {}",
+            report.current_context()
+        ))),
+    }
+}
+
+/// Fetches a feed, previewing how its newest entry is announced.
+async fn test(url: &str) -> Result<CommandResponse, Report<CommandError>> {
+    let feed = match fetch(url).await {
+        Ok(feed) => feed,
+        Err(response) => return Ok(response),
+    };
+
+    let title = escape_mentions(
+        feed.title
+            .as_ref()
+            .map(|title| title.content.as_str())
+            .unwrap_or(url),
+    );
+
+    // feeds list their newest entries first
+    let Some(entry) = feed.entries.first() else {
+        return Ok(CommandResponse::text(format!(
+            "Read **{title}**, which has no entries yet."
+        )));
+    };
+
+    let message =
+        announcement_message(vec![], &feed, entry, false).change_context(CommandError::Feed)?;
+    let attached = match message.files.is_empty() {
+        true => "",
+        false => " (with its full text attached, as it is too long)",
+    };
+
+    Ok(CommandResponse {
+        content: format!(
+            "Read **{title}**, which has {} entries. Its newest entry would be announced as{attached}:",
+            feed.entries.len()
+        ),
+        embeds: message.embeds,
+    })
+}
+
+/// Manages the filter rules of the feeds.
+async fn filter(
+    options: &[CommandDataOption],
+    pool: &SqlitePool,
+) -> Result<CommandResponse, Report<CommandError>> {
+    let (name, options) = subcommand(options)?;
+
+    let string = |name: &str| match option(options, name) {
+        Some(CommandOptionValue::String(value)) => Some(value.trim()),
+        _ => None,
+    };
+
+    match name {
+        "add" => {
+            let (Some(url), Some(action), Some(kind), Some(pattern)) = (
+                string("url"),
+                string("action"),
+                string("kind"),
+                string("pattern"),
+            ) else {
+                return Err(Report::new(CommandError::InvalidOptions))
+                    .attach("Expected the URL, action, kind and pattern of the rule");
+            };
+            let action = action
+                .parse()
+                .change_context(CommandError::InvalidOptions)?;
+            let kind = kind.parse().change_context(CommandError::InvalidOptions)?;
+
+            if let Some(response) = invalid_pattern(kind, pattern) {
+                return Ok(response);
+            }
+
+            let id = add_filter(url, action, kind, pattern, pool)
+                .await
+                .change_context(CommandError::Database)?;
+
+            Ok(CommandResponse::text(match id {
+                Some(id) => format!(
+                    "Added rule #{id} to {url}. Use `/feed dry-run` to see which entries it lets through."
+                ),
+                None => format!("There is no feed with the URL {url}."),
+            }))
+        }
+        "remove" => {
+            let Some(CommandOptionValue::Integer(id)) = option(options, "id") else {
+                return Err(Report::new(CommandError::InvalidOptions))
+                    .attach("Expected the number of the rule to remove");
+            };
+
+            let removed = remove_filter(*id, pool)
+                .await
+                .change_context(CommandError::Database)?;
+
+            Ok(CommandResponse::text(match removed {
+                true => format!("Removed rule #{id}."),
+                false => format!("There is no rule #{id}."),
+            }))
+        }
+        "list" => {
+            let Some(url) = string("url") else {
+                return Err(Report::new(CommandError::InvalidOptions))
+                    .attach("Expected the URL of the feed");
+            };
+
+            let rules = list_filters(url, pool)
+                .await
+                .change_context(CommandError::Database)?;
+            if rules.is_empty() {
+                return Ok(CommandResponse::text(format!(
+                    "{url} has no filter rules, so all of its entries are announced."
+                )));
+            }
+
+            let lines = rules.iter().map(ToString::to_string).collect::<Vec<_>>();
+            Ok(CommandResponse {
+                content: String::new(),
+                embeds: vec![filter_embed(
+                    format!("Filter rules of {url}"),
+                    join_lines(&lines, MAX_DESCRIPTION_LENGTH),
+                )],
+            })
+        }
+        _ => Err(Report::new(CommandError::InvalidOptions))
+            .attach(format!("Unknown filter subcommand {name}")),
+    }
+}
+
+/// Manages the rules deciding which roles announcements ping.
+async fn ping(
+    options: &[CommandDataOption],
+    pool: &SqlitePool,
+) -> Result<CommandResponse, Report<CommandError>> {
+    let (name, options) = subcommand(options)?;
+
+    let string = |name: &str| match option(options, name) {
+        Some(CommandOptionValue::String(value)) => Some(value.trim()),
+        _ => None,
+    };
+
+    match name {
+        "add" => {
+            let (Some(kind), Some(pattern), Some(CommandOptionValue::Role(role_id))) =
+                (string("kind"), string("pattern"), option(options, "role"))
+            else {
+                return Err(Report::new(CommandError::InvalidOptions))
+                    .attach("Expected the kind, pattern and role of the rule");
+            };
+            let kind = kind.parse().change_context(CommandError::InvalidOptions)?;
+            let url = string("url");
+
+            if let Some(response) = invalid_pattern(kind, pattern) {
+                return Ok(response);
+            }
+
+            let id = add_ping(url, kind, pattern, *role_id, pool)
+                .await
+                .change_context(CommandError::Database)?;
+
+            Ok(CommandResponse::text(match (id, url) {
+                (Some(id), Some(url)) => format!(
+                    "Added rule #{id}, pinging <@&{role_id}> for the matching announcements of {url}."
+                ),
+                (Some(id), None) => format!(
+                    "Added rule #{id}, pinging <@&{role_id}> for the matching announcements of every feed."
+                ),
+                (None, _) => format!("There is no feed with the URL {}.", url.unwrap_or_default()),
+            }))
+        }
+        "remove" => {
+            let Some(CommandOptionValue::Integer(id)) = option(options, "id") else {
+                return Err(Report::new(CommandError::InvalidOptions))
+                    .attach("Expected the number of the rule to remove");
+            };
+
+            let removed = remove_ping(*id, pool)
+                .await
+                .change_context(CommandError::Database)?;
+
+            Ok(CommandResponse::text(match removed {
+                true => format!("Removed rule #{id}."),
+                false => format!("There is no rule #{id}."),
+            }))
+        }
+        "list" => {
+            let url = string("url");
+            let rules = list_pings(url, pool)
+                .await
+                .change_context(CommandError::Database)?;
+            if rules.is_empty() {
+                return Ok(CommandResponse::text(
+                    "There are no ping rules, so announcements only ping the role of their feed.",
+                ));
+            }
+
+            let lines = rules.iter().map(ToString::to_string).collect::<Vec<_>>();
+            Ok(CommandResponse {
+                content: String::new(),
+                embeds: vec![filter_embed(
+                    match url {
+                        Some(url) => format!("Ping rules of {url}"),
+                        None => "Ping rules".to_string(),
+                    },
+                    join_lines(&lines, MAX_DESCRIPTION_LENGTH),
+                )],
+            })
+        }
+        _ => Err(Report::new(CommandError::InvalidOptions))
+            .attach(format!("Unknown ping subcommand {name}")),
+    }
+}
+
+/// Explains why the `pattern` of a new rule cannot be used, if it is an invalid regular expression.
+fn invalid_pattern(kind: FilterKind, pattern: &str) -> Option<CommandResponse> {
+    if kind != FilterKind::Regex {
+        return None;
+    }
+
+    Regex::new(pattern).err().map(|error| {
+        CommandResponse::text(format!(
+            "That is not a valid regular expression:\n```\n{error}\n```"
+        ))
+    })
+}
+
+/// Fetches a feed, showing which of its entries would be announced under its filter rules, and
+/// which roles its ping rules would ping for them.
+async fn dry_run(url: &str, pool: &SqlitePool) -> Result<CommandResponse, Report<CommandError>> {
+    let rules = match feed_filter(url, pool).await {
+        Ok(filter) => feed_pings(url, pool).await.map(|pings| (filter, pings)),
+        Err(report) => Err(report),
+    };
+    let (filter, pings) = match rules {
+        Ok(rules) => rules,
+        Err(report) if matches!(report.current_context(), RssError::Filter) => {
+            log::warn!("Failed to apply the rules of {url}: {report:?}");
+            return Ok(CommandResponse::text(format!(
+                "The rules of {url} could not be applied, remove the invalid rule and try again."
+            )));
+        }
+        Err(report) => return Err(report.change_context(CommandError::Database)),
+    };
+
+    let feed = match fetch(url).await {
+        Ok(feed) => feed,
+        Err(response) => return Ok(response),
+    };
+
+    if feed.entries.is_empty() {
+        return Ok(CommandResponse::text(format!("{url} has no entries yet.")));
+    }
+
+    Ok(CommandResponse {
+        content: match filter.is_empty() {
+            true => format!("{url} has no filter rules, so all of its entries are announced."),
+            false => String::new(),
+        },
+        embeds: vec![filter_embed(
+            format!("Dry run of {url}"),
+            dry_run_lines(&feed, &filter, &pings),
+        )],
+    })
+}
+
+/// Lists the entries of a feed, newest first, marking whether each of them would be announced and
+/// the roles their ping rules would ping.
+fn dry_run_lines(feed: &Feed, filter: &FeedFilter, pings: &FeedPings) -> String {
+    let lines = feed
+        .entries
+        .iter()
+        .map(|entry| {
+            let title = escape_mentions(
+                entry
+                    .title
+                    .as_ref()
+                    .map(|title| title.content.as_str())
+                    .unwrap_or(&entry.id),
+            );
+
+            let roles = pings
+                .roles(None, entry)
+                .iter()
+                .map(|role_id| format!("<@&{role_id}>"))
+                .collect::<Vec<_>>();
+
+            match (filter.check(entry), roles.is_empty()) {
+                (Ok(()), true) => format!("✅ {title}"),
+                (Ok(()), false) => format!("✅ {title}, pinging {}", roles.join(" ")),
+                (Err(filtered), _) => format!("🚫 {title} ({filtered})"),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    join_lines(&lines, MAX_DESCRIPTION_LENGTH)
+}
+
+/// An embed listing the filter rules of a feed, or what they do to its entries.
+fn filter_embed(title: String, description: String) -> Embed {
+    Embed {
+        author: None,
+        color: Some(15844367),
+        description: Some(description),
+        fields: vec![],
+        footer: None,
+        timestamp: None,
+        kind: "rich".to_string(),
+        image: None,
+        provider: None,
+        thumbnail: None,
+        title: Some(title),
+        url: None,
+        video: None,
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use error_stack::{Report, ResultExt};
+use sqlx::SqlitePool;
+use twilight_http::Client;
+use twilight_model::{
+    application::{
+        command::Command,
+        interaction::{
+            application_command::{CommandData, CommandDataOption, CommandOptionValue},
+            Interaction,
+        },
+    },
+    channel::message::Embed,
+};
+
+use crate::{config::ApplicationConfig, error::CommandError};
+
+mod about;
+mod feed;
+mod starboard;
+mod starboard_admin;
+
+/// The response sent back to the user that used a command.
+pub struct CommandResponse {
+    /// The content of the response.
+    pub content: String,
+    /// The embeds to attach to the response.
+    pub embeds: Vec<Embed>,
+}
+
+impl CommandResponse {
+    /// Creates a response that consists only of text.
+    pub fn text(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            embeds: vec![],
+        }
+    }
+}
+
+/// The definitions of every command the bot provides.
+fn definitions(config: &ApplicationConfig) -> Vec<Command> {
+    vec![
+        about::definition(),
+        feed::definition(),
+        starboard::definition(config),
+        starboard_admin::definition(),
+    ]
+}
+
+/// Retrieves the name of the subcommand (or subcommand group) used, along with the options given
+/// to it.
+fn subcommand(
+    options: &[CommandDataOption],
+) -> Result<(&str, &[CommandDataOption]), Report<CommandError>> {
+    match options.first() {
+        Some(CommandDataOption {
+            name,
+            value:
+                CommandOptionValue::SubCommand(options) | CommandOptionValue::SubCommandGroup(options),
+        }) => Ok((name, options)),
+        _ => Err(Report::new(CommandError::InvalidOptions)).attach("Expected a subcommand"),
+    }
+}
+
+/// Finds the value given to the option with the `name`, if the option was given.
+fn option<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a CommandOptionValue> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .map(|option| &option.value)
+}
+
+/// Registers every command the bot provides with Discord.
+///
+/// Commands are registered to the tracked server if one is configured, as they become available
+/// there immediately. Otherwise, they are registered globally.
+pub async fn register_commands(
+    http: &Client,
+    config: &ApplicationConfig,
+) -> Result<(), Report<CommandError>> {
+    let application_id = http
+        .current_user_application()
+        .await
+        .change_context(CommandError::Register)?
+        .model()
+        .await
+        .change_context(CommandError::Register)?
+        .id;
+    let interaction = http.interaction(application_id);
+
+    let commands = definitions(config);
+    match config.server_id {
+        Some(server_id) => {
+            interaction
+                .set_guild_commands(server_id, &commands)
+                .await
+                .change_context(CommandError::Register)?;
+        }
+        None => {
+            interaction
+                .set_global_commands(&commands)
+                .await
+                .change_context(CommandError::Register)?;
+        }
+    }
+    log::info!("Registered {} application commands", commands.len());
+
+    Ok(())
+}
+
+/// Whether the response to the command with the given `name` is only shown to the user that used it.
+pub fn is_ephemeral(name: &str) -> bool {
+    match name {
+        about::NAME => about::EPHEMERAL,
+        feed::NAME => feed::EPHEMERAL,
+        starboard::NAME => starboard::EPHEMERAL,
+        starboard_admin::NAME => starboard_admin::EPHEMERAL,
+        _ => true,
+    }
+}
+
+/// Runs the command used, returning the response to show to the user.
+pub async fn handle_command(
+    interaction: &Interaction,
+    command: &CommandData,
+    http: Arc<Client>,
+    pool: SqlitePool,
+    config: Arc<ApplicationConfig>,
+) -> Result<CommandResponse, Report<CommandError>> {
+    match command.name.as_str() {
+        about::NAME => about::run(&config).await,
+        feed::NAME => feed::run(command, &pool, &config).await,
+        starboard::NAME => starboard::run(interaction, command, &pool).await,
+        starboard_admin::NAME => starboard_admin::run(command, http, pool, config).await,
+        _ => Err(Report::new(CommandError::UnknownCommand))
+            .attach(format!("Unknown command /{}", command.name)),
+    }
+}
@@ -0,0 +1,400 @@
+use chrono::{Duration, Utc};
+use error_stack::{Report, ResultExt};
+use sqlx::SqlitePool;
+use twilight_model::{
+    application::{
+        command::{Command, CommandType},
+        interaction::{
+            application_command::{CommandData, CommandOptionValue},
+            Interaction,
+        },
+    },
+    channel::message::{embed::EmbedField, Embed},
+    id::{
+        marker::{ChannelMarker, GuildMarker, MessageMarker},
+        Id,
+    },
+};
+use twilight_util::builder::command::{
+    CommandBuilder, StringBuilder, SubCommandBuilder, UserBuilder,
+};
+
+use crate::{
+    config::ApplicationConfig, create_starboard_message::message_link, error::CommandError,
+};
+
+use super::{option, subcommand, CommandResponse};
+
+pub const NAME: &str = "starboard";
+
+/// Whether the response is only shown to the user that used the command.
+pub const EPHEMERAL: bool = false;
+
+/// The amount of messages listed in the leaderboard.
+const LEADERBOARD_SIZE: i64 = 10;
+
+/// The amount of members listed as top authors in the server statistics.
+const TOP_AUTHORS_SIZE: i64 = 5;
+
+pub fn definition(config: &ApplicationConfig) -> Command {
+    let board_option = || {
+        StringBuilder::new("board", "Only include posts on this starboard").choices(
+            config
+                .starboards
+                .iter()
+                .map(|board| (board.name.clone(), board.name.clone())),
+        )
+    };
+
+    CommandBuilder::new(
+        NAME,
+        "Leaderboards and statistics of the starboard",
+        CommandType::ChatInput,
+    )
+    .option(
+        SubCommandBuilder::new("top", "Lists the most reacted to starboard posts")
+            .option(
+                StringBuilder::new("period", "Only include messages sent within this period")
+                    .choices([
+                        ("Today", "day"),
+                        ("This week", "week"),
+                        ("This month", "month"),
+                        ("This year", "year"),
+                        ("All time", "all"),
+                    ]),
+            )
+            .option(board_option()),
+    )
+    .option(
+        SubCommandBuilder::new("user", "Shows how often a member has made the starboard")
+            .option(UserBuilder::new("member", "The member to show statistics of").required(true))
+            .option(board_option()),
+    )
+    .option(SubCommandBuilder::new(
+        "stats",
+        "Shows statistics of the starboard for the whole server",
+    ))
+    .build()
+}
+
+/// Shows leaderboards and statistics of the starboard.
+pub async fn run(
+    interaction: &Interaction,
+    command: &CommandData,
+    pool: &SqlitePool,
+) -> Result<CommandResponse, Report<CommandError>> {
+    let (name, options) = subcommand(&command.options)?;
+
+    let board = match option(options, "board") {
+        Some(CommandOptionValue::String(board)) => Some(board.as_str()),
+        _ => None,
+    };
+
+    match name {
+        "top" => {
+            let period = match option(options, "period") {
+                Some(CommandOptionValue::String(period)) => period.as_str(),
+                _ => "all",
+            };
+
+            top(interaction.guild_id, period, board, pool).await
+        }
+        "user" => {
+            let Some(CommandOptionValue::User(member)) = option(options, "member") else {
+                return Err(Report::new(CommandError::InvalidOptions))
+                    .attach("Expected a member to show statistics of");
+            };
+
+            user(interaction.guild_id, &member.to_string(), board, pool).await
+        }
+        "stats" => stats(pool).await,
+        _ => Err(Report::new(CommandError::InvalidOptions))
+            .attach(format!("Unknown subcommand {name}")),
+    }
+}
+
+/// Describes a single starboard post, linking to the original message if it is known.
+fn describe_post(
+    guild_id: Option<Id<GuildMarker>>,
+    message_id: i64,
+    channel_id: Option<i64>,
+    emoji: Option<&str>,
+    reaction_count: i64,
+) -> String {
+    let reactions = format!("{reaction_count} {}", emoji.unwrap_or("reactions"));
+
+    let message_id = u64::try_from(message_id)
+        .ok()
+        .and_then(Id::<MessageMarker>::new_checked);
+    let channel_id = channel_id
+        .and_then(|channel_id| u64::try_from(channel_id).ok())
+        .and_then(Id::<ChannelMarker>::new_checked);
+
+    match message_id.zip(channel_id) {
+        Some((message_id, channel_id)) => format!(
+            "{reactions} in <#{channel_id}> ([jump]({}))",
+            message_link(guild_id, channel_id, message_id)
+        ),
+        None => reactions,
+    }
+}
+
+/// Lists the most reacted to starboard posts of messages sent within the `period`.
+async fn top(
+    guild_id: Option<Id<GuildMarker>>,
+    period: &str,
+    board: Option<&str>,
+    pool: &SqlitePool,
+) -> Result<CommandResponse, Report<CommandError>> {
+    let since = match period {
+        "day" => Some(Duration::days(1)),
+        "week" => Some(Duration::weeks(1)),
+        "month" => Some(Duration::days(30)),
+        "year" => Some(Duration::days(365)),
+        _ => None,
+    }
+    .map(|period| (Utc::now() - period).timestamp_millis())
+    .unwrap_or_default();
+
+    let posts = sqlx::query!(
+        r#"
+SELECT message_id, channel_id, author_id, board, emoji, reaction_count
+FROM starboard
+WHERE COALESCE(message_timestamp, 0) >= ? AND (? IS NULL OR board = ?)
+ORDER BY reaction_count DESC
+LIMIT ?
+	"#,
+        since,
+        board,
+        board,
+        LEADERBOARD_SIZE
+    )
+    .fetch_all(pool)
+    .await
+    .change_context(CommandError::Database)?;
+
+    if posts.is_empty() {
+        return Ok(CommandResponse::text(
+            "No messages have made the starboard in this period yet.",
+        ));
+    }
+
+    let description = posts
+        .into_iter()
+        .enumerate()
+        .map(|(rank, post)| {
+            format!(
+                "{}. {}{} on {}",
+                rank + 1,
+                describe_post(
+                    guild_id,
+                    post.message_id,
+                    post.channel_id,
+                    post.emoji.as_deref(),
+                    post.reaction_count
+                ),
+                post.author_id
+                    .map(|author_id| format!(" by <@{author_id}>"))
+                    .unwrap_or_default(),
+                post.board
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(CommandResponse {
+        content: String::new(),
+        embeds: vec![Embed {
+            author: None,
+            color: Some(15844367),
+            description: Some(description),
+            fields: vec![],
+            footer: None,
+            timestamp: None,
+            kind: "rich".to_string(),
+            image: None,
+            provider: None,
+            thumbnail: None,
+            title: Some("Starboard leaderboard".to_string()),
+            url: None,
+            video: None,
+        }],
+    })
+}
+
+/// Shows how many times the `member` has made the starboard, and their best post.
+async fn user(
+    guild_id: Option<Id<GuildMarker>>,
+    member: &str,
+    board: Option<&str>,
+    pool: &SqlitePool,
+) -> Result<CommandResponse, Report<CommandError>> {
+    let totals = sqlx::query!(
+        r#"
+SELECT COUNT(*) AS "posts!: i64", COALESCE(SUM(reaction_count), 0) AS "reactions!: i64"
+FROM starboard
+WHERE author_id = ? AND (? IS NULL OR board = ?)
+	"#,
+        member,
+        board,
+        board
+    )
+    .fetch_one(pool)
+    .await
+    .change_context(CommandError::Database)?;
+
+    let best_post = sqlx::query!(
+        r#"
+SELECT message_id, channel_id, board, emoji, reaction_count
+FROM starboard
+WHERE author_id = ? AND (? IS NULL OR board = ?)
+ORDER BY reaction_count DESC
+LIMIT 1
+	"#,
+        member,
+        board,
+        board
+    )
+    .fetch_optional(pool)
+    .await
+    .change_context(CommandError::Database)?;
+
+    let Some(best_post) = best_post else {
+        return Ok(CommandResponse::text(format!(
+            "<@{member}> has not made the starboard yet."
+        )));
+    };
+
+    Ok(CommandResponse {
+        content: String::new(),
+        embeds: vec![Embed {
+            author: None,
+            color: Some(15844367),
+            description: Some(format!("Starboard statistics of <@{member}>")),
+            fields: vec![
+                EmbedField {
+                    inline: true,
+                    name: "Starboard posts".to_string(),
+                    value: totals.posts.to_string(),
+                },
+                EmbedField {
+                    inline: true,
+                    name: "Reactions received".to_string(),
+                    value: totals.reactions.to_string(),
+                },
+                EmbedField {
+                    inline: false,
+                    name: "Best post".to_string(),
+                    value: format!(
+                        "{} on {}",
+                        describe_post(
+                            guild_id,
+                            best_post.message_id,
+                            best_post.channel_id,
+                            best_post.emoji.as_deref(),
+                            best_post.reaction_count
+                        ),
+                        best_post.board
+                    ),
+                },
+            ],
+            footer: None,
+            timestamp: None,
+            kind: "rich".to_string(),
+            image: None,
+            provider: None,
+            thumbnail: None,
+            title: None,
+            url: None,
+            video: None,
+        }],
+    })
+}
+
+/// Shows statistics of every starboard in the server.
+async fn stats(pool: &SqlitePool) -> Result<CommandResponse, Report<CommandError>> {
+    let boards = sqlx::query!(
+        r#"
+SELECT board, COUNT(*) AS "posts!: i64", COALESCE(SUM(reaction_count), 0) AS "reactions!: i64", COUNT(DISTINCT author_id) AS "authors!: i64"
+FROM starboard
+GROUP BY board
+ORDER BY board
+	"#
+    )
+    .fetch_all(pool)
+    .await
+    .change_context(CommandError::Database)?;
+
+    let top_authors = sqlx::query!(
+        r#"
+SELECT author_id AS "author_id!: i64", COUNT(*) AS "posts!: i64", COALESCE(SUM(reaction_count), 0) AS "reactions!: i64"
+FROM starboard
+WHERE author_id IS NOT NULL
+GROUP BY author_id
+ORDER BY 3 DESC
+LIMIT ?
+	"#,
+        TOP_AUTHORS_SIZE
+    )
+    .fetch_all(pool)
+    .await
+    .change_context(CommandError::Database)?;
+
+    if boards.is_empty() {
+        return Ok(CommandResponse::text(
+            "No messages have made the starboard yet.",
+        ));
+    }
+
+    let mut fields = boards
+        .into_iter()
+        .map(|board| EmbedField {
+            inline: true,
+            name: board.board,
+            value: format!(
+                "{} posts\n{} reactions\n{} authors",
+                board.posts, board.reactions, board.authors
+            ),
+        })
+        .collect::<Vec<_>>();
+
+    if !top_authors.is_empty() {
+        fields.push(EmbedField {
+            inline: false,
+            name: "Top authors".to_string(),
+            value: top_authors
+                .into_iter()
+                .enumerate()
+                .map(|(rank, author)| {
+                    format!(
+                        "{}. <@{}> with {} reactions over {} posts",
+                        rank + 1,
+                        author.author_id,
+                        author.reactions,
+                        author.posts
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        });
+    }
+
+    Ok(CommandResponse {
+        content: String::new(),
+        embeds: vec![Embed {
+            author: None,
+            color: Some(15844367),
+            description: None,
+            fields,
+            footer: None,
+            timestamp: None,
+            kind: "rich".to_string(),
+            image: None,
+            provider: None,
+            thumbnail: None,
+            title: Some("Starboard statistics".to_string()),
+            url: None,
+            video: None,
+        }],
+    })
+}
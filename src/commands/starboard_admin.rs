@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use error_stack::{Report, ResultExt};
+use sqlx::SqlitePool;
+use twilight_http::Client;
+use twilight_model::{
+    application::{
+        command::{Command, CommandType},
+        interaction::application_command::{CommandData, CommandDataOption, CommandOptionValue},
+    },
+    channel::ChannelType,
+    guild::Permissions,
+    id::{
+        marker::{ChannelMarker, MessageMarker},
+        Id,
+    },
+};
+use twilight_util::builder::command::{
+    BooleanBuilder, ChannelBuilder, CommandBuilder, StringBuilder, SubCommandBuilder,
+    SubCommandGroupBuilder, UserBuilder,
+};
+
+use crate::{
+    config::ApplicationConfig,
+    error::CommandError,
+    starboard::{block, start_backfill, unblock, veto_message, withdraw_blocked_posts, BlockKind},
+};
+
+use super::{option, subcommand, CommandResponse};
+
+pub const NAME: &str = "starboard-admin";
+
+/// Whether the response is only shown to the user that used the command.
+pub const EPHEMERAL: bool = true;
+
+pub fn definition() -> Command {
+    CommandBuilder::new(
+        NAME,
+        "Moderate and maintain the starboard",
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::MANAGE_GUILD)
+    .dm_permission(false)
+    .option(
+        SubCommandBuilder::new(
+            "backfill",
+            "Posts messages from the history of a channel that qualify for the starboard",
+        )
+        .option(
+            ChannelBuilder::new("channel", "The channel to read the history of")
+                .channel_types([
+                    ChannelType::GuildText,
+                    ChannelType::GuildAnnouncement,
+                    ChannelType::PublicThread,
+                ])
+                .required(true),
+        )
+        .option(BooleanBuilder::new(
+            "restart",
+            "Evaluate the whole history again, rather than continuing from the last backfill",
+        )),
+    )
+    .option(blocklist_group(
+        "block",
+        "Keeps a user, channel or message off the starboard",
+    ))
+    .option(blocklist_group(
+        "unblock",
+        "Allows a blocked user, channel or message onto the starboard again",
+    ))
+    .build()
+}
+
+/// Builds the group of subcommands that add to or remove from the starboard blocklist.
+fn blocklist_group(name: &str, description: &str) -> SubCommandGroupBuilder {
+    SubCommandGroupBuilder::new(name, description).subcommands([
+        SubCommandBuilder::new("user", "Messages sent by a user")
+            .option(UserBuilder::new("user", "The user").required(true)),
+        SubCommandBuilder::new(
+            "channel",
+            "Messages sent in a channel, or a category of channels",
+        )
+        .option(
+            ChannelBuilder::new("channel", "The channel or category")
+                .channel_types([
+                    ChannelType::GuildCategory,
+                    ChannelType::GuildText,
+                    ChannelType::GuildAnnouncement,
+                    ChannelType::GuildForum,
+                    ChannelType::PublicThread,
+                ])
+                .required(true),
+        ),
+        SubCommandBuilder::new("message", "A single message")
+            .option(StringBuilder::new("link", "The link to the message").required(true)),
+    ])
+}
+
+/// Parses the channel and message IDs out of a message link, such as
+/// `https://discord.com/channels/<guild_id>/<channel_id>/<message_id>`.
+fn parse_message_link(link: &str) -> Option<(Id<ChannelMarker>, Id<MessageMarker>)> {
+    let mut parts = link.trim().trim_end_matches('/').rsplit('/');
+    let message_id = parts.next()?.parse().ok()?;
+    let channel_id = parts.next()?.parse().ok()?;
+
+    Some((Id::new_checked(channel_id)?, Id::new_checked(message_id)?))
+}
+
+/// Adds the target of a `block` or `unblock` subcommand to the blocklist, or removes it.
+async fn update_blocklist(
+    blocking: bool,
+    kind: &str,
+    options: &[CommandDataOption],
+    http: &Client,
+    pool: &SqlitePool,
+    config: &ApplicationConfig,
+) -> Result<CommandResponse, Report<CommandError>> {
+    let (kind, target_id, mention) = match (kind, option(options, kind)) {
+        ("user", Some(CommandOptionValue::User(user_id))) => {
+            (BlockKind::User, user_id.get(), format!("<@{user_id}>"))
+        }
+        ("channel", Some(CommandOptionValue::Channel(channel_id))) => (
+            BlockKind::Channel,
+            channel_id.get(),
+            format!("<#{channel_id}>"),
+        ),
+        ("message", Some(CommandOptionValue::String(link))) => {
+            let Some((channel_id, message_id)) = parse_message_link(link) else {
+                return Ok(CommandResponse::text(format!(
+                    "`{link}` is not a message link. Use \"Copy Message Link\" on the message."
+                )));
+            };
+
+            // blocking a message also takes down its existing posts
+            if blocking {
+                veto_message(channel_id, message_id, http, pool, config)
+                    .await
+                    .change_context(CommandError::Starboard)?;
+
+                return Ok(CommandResponse::text(format!(
+                    "{link} was removed from the starboard, and will not be posted again."
+                )));
+            }
+
+            (BlockKind::Message, message_id.get(), link.clone())
+        }
+        _ => {
+            return Err(Report::new(CommandError::InvalidOptions))
+                .attach(format!("Expected a {kind} to update the blocklist with"))
+        }
+    };
+
+    let changed = match blocking {
+        true => block(kind, target_id, pool).await,
+        false => unblock(kind, target_id, pool).await,
+    }
+    .change_context(CommandError::Database)?;
+
+    // blocking also takes down the posts made before, even if a previous attempt was cut short
+    let withdrawn = match blocking {
+        true => withdraw_blocked_posts(kind, target_id, http, pool, config)
+            .await
+            .change_context(CommandError::Starboard)?,
+        false => 0,
+    };
+
+    let status = match (blocking, changed) {
+        (true, true) => format!("{mention} is now blocked from the starboard."),
+        (true, false) => format!("{mention} is already blocked from the starboard."),
+        (false, true) => format!("{mention} is no longer blocked from the starboard."),
+        (false, false) => format!("{mention} is not blocked from the starboard."),
+    };
+
+    Ok(CommandResponse::text(match withdrawn {
+        0 => status,
+        _ => format!("{status} {withdrawn} of its starboard'd messages were taken down."),
+    }))
+}
+
+/// Runs moderation and maintenance tasks for the starboard.
+pub async fn run(
+    command: &CommandData,
+    http: Arc<Client>,
+    pool: SqlitePool,
+    config: Arc<ApplicationConfig>,
+) -> Result<CommandResponse, Report<CommandError>> {
+    let (name, options) = subcommand(&command.options)?;
+
+    match name {
+        "backfill" => {
+            let Some(CommandOptionValue::Channel(channel_id)) = option(options, "channel") else {
+                return Err(Report::new(CommandError::InvalidOptions))
+                    .attach("Expected a channel to backfill");
+            };
+            let restart = matches!(
+                option(options, "restart"),
+                Some(CommandOptionValue::Boolean(true))
+            );
+
+            let started = start_backfill(*channel_id, restart, http, pool, config)
+                .await
+                .change_context(CommandError::Database)?;
+
+            Ok(CommandResponse::text(match started {
+                true => format!("Started backfilling the starboard from the history of <#{channel_id}>. Messages are posted in the order they were sent."),
+                false => format!("<#{channel_id}> is already being backfilled."),
+            }))
+        }
+        "block" | "unblock" => {
+            let (kind, options) = subcommand(options)?;
+
+            update_blocklist(name == "block", kind, options, &http, &pool, &config).await
+        }
+        _ => Err(Report::new(CommandError::InvalidOptions))
+            .attach(format!("Unknown subcommand {name}")),
+    }
+}
@@ -1,14 +1,179 @@
-use std::{env, time::Duration};
+use std::{env, fmt::Display, time::Duration};
 
+use chrono::{DateTime, NaiveTime, TimeDelta, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
 use error_stack::{Report, ResultExt};
-use twilight_model::id::{
-    marker::{ChannelMarker, GuildMarker, RoleMarker},
-    Id,
+use twilight_model::{
+    channel::thread::AutoArchiveDuration,
+    id::{
+        marker::{ChannelMarker, GuildMarker, RoleMarker},
+        Id,
+    },
 };
 
 use crate::error::ConfigError;
 
-type ParsedRssUrl = (String, Id<ChannelMarker>, Option<Id<RoleMarker>>);
+/// The name of the starboard used when `STARBOARDS` is not specified.
+///
+/// Starboard posts made before multiple boards were supported belong to this board.
+const DEFAULT_STARBOARD_NAME: &str = "default";
+
+/// The most gateway events handled at the same time, when `EVENT_CONCURRENCY` is not specified.
+const DEFAULT_EVENT_CONCURRENCY: usize = 16;
+
+/// The most announcement feeds fetched at the same time, when `ANNOUNCEMENT_FETCH_CONCURRENCY` is
+/// not specified.
+const DEFAULT_ANNOUNCEMENT_FETCH_CONCURRENCY: usize = 4;
+
+/// An RSS feed whose entries are announced in a channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnouncementFeed {
+    /// The URL of the feed.
+    pub url: String,
+    /// The channel to post announcements to.
+    pub channel_id: Id<ChannelMarker>,
+    /// The role to ping when announcements are made, if any.
+    pub role_id: Option<Id<RoleMarker>>,
+    /// A name for the feed to recognise it by, such as the course it belongs to.
+    pub label: Option<String>,
+    /// How long to wait between checks of the feed for new announcements. If not specified, the
+    /// `announcement_check_interval` is used.
+    pub check_interval: Option<Duration>,
+    /// How long the discussion thread opened on each announcement stays open without activity, if
+    /// threads are opened at all.
+    pub thread_archive_duration: Option<AutoArchiveDuration>,
+    /// The name and avatar to post announcements with through a webhook, if the feed is not posted
+    /// by the bot itself.
+    pub webhook: Option<WebhookIdentity>,
+    /// When new entries are gathered into a digest, if they are not announced as soon as they are
+    /// found.
+    pub digest: Option<DigestSchedule>,
+}
+
+/// When the digest of a channel's announcements is posted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DigestSchedule {
+    /// At the start of every hour.
+    Hourly,
+    /// Every day at the same time, in the `DIGEST_TIMEZONE`.
+    Daily { time: NaiveTime },
+}
+
+impl DigestSchedule {
+    /// Parses a schedule such as `hourly` or `daily 08:00`.
+    pub fn parse(schedule: &str) -> Option<Self> {
+        let mut parts = schedule.split_whitespace();
+
+        let schedule = match (parts.next()?.to_lowercase().as_str(), parts.next()) {
+            ("hourly", None) => Self::Hourly,
+            ("daily", Some(time)) => Self::Daily {
+                time: NaiveTime::parse_from_str(time, "%H:%M").ok()?,
+            },
+            _ => return None,
+        };
+
+        parts.next().is_none().then_some(schedule)
+    }
+
+    /// The first time the digest is posted after `after`.
+    pub fn next_time(&self, after: DateTime<Utc>, timezone: Tz) -> DateTime<Utc> {
+        let local = after.with_timezone(&timezone).naive_local();
+
+        let (mut next, step) = match self {
+            Self::Hourly => (
+                local.date().and_time(NaiveTime::MIN) + TimeDelta::hours(local.hour().into()),
+                TimeDelta::hours(1),
+            ),
+            Self::Daily { time } => (local.date().and_time(*time), TimeDelta::days(1)),
+        };
+
+        loop {
+            // times skipped by daylight saving are moved to just after the clocks change
+            let time = timezone.from_local_datetime(&next).earliest().or_else(|| {
+                timezone
+                    .from_local_datetime(&(next + TimeDelta::hours(1)))
+                    .earliest()
+            });
+            if let Some(time) = time {
+                let time = time.with_timezone(&Utc);
+                if time > after {
+                    return time;
+                }
+            }
+            next += step;
+        }
+    }
+}
+
+impl Display for DigestSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Hourly => write!(f, "hourly"),
+            Self::Daily { time } => write!(f, "daily {}", time.format("%H:%M")),
+        }
+    }
+}
+
+/// How announcements posted through a webhook appear.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookIdentity {
+    /// The name to post with. If not specified, the label of the feed or its course is used.
+    pub name: Option<String>,
+    /// The URL of the avatar to post with. If not specified, the avatar of the webhook is used.
+    pub avatar_url: Option<String>,
+}
+
+/// What to do with a starboard post once the message it refers to has been deleted.
+#[derive(Debug, Clone, Copy)]
+pub enum DeletedMessagePolicy {
+    /// Delete the starboard post.
+    Delete,
+    /// Keep the starboard post, but replace its contents with a notice that the message was deleted.
+    Tombstone,
+}
+
+/// How the reactions on a message are counted towards the reaction requirement.
+#[derive(Debug, Clone)]
+pub enum CountingMode {
+    /// The most users that reacted with any single emoji.
+    SingleEmoji,
+    /// The amount of distinct users that reacted with any emoji.
+    UniqueUsers,
+}
+
+/// A starboard that messages are posted to once they receive enough reactions.
+///
+/// A message is evaluated against every board independently, so it may be posted to several boards.
+#[derive(Debug, Clone)]
+pub struct Starboard {
+    /// The unique name of the board, used to remember which boards a message has been posted to.
+    pub name: String,
+    /// The channel to post starboard messages into.
+    pub channel_id: Id<ChannelMarker>,
+    /// The amount of unique reactions (not including message author) to a message to make it material for this board.
+    pub reaction_requirement: u32,
+    /// The amount of reactions a message must fall below for its post on this board to be removed.
+    ///
+    /// If not specified, posts on this board are never removed once made.
+    pub removal_threshold: Option<u32>,
+    /// The emojis that are counted for this board. Each is either a unicode emoji, or the name or ID
+    /// of a custom emoji. If empty, reactions with any emoji are counted.
+    pub emojis: Vec<String>,
+    /// The channels (or categories) that messages must be in to be posted to this board. If empty,
+    /// messages from any channel are posted.
+    pub include_channels: Vec<Id<ChannelMarker>>,
+    /// The channels (or categories) whose messages are never posted to this board.
+    pub exclude_channels: Vec<Id<ChannelMarker>>,
+}
+
+/// Lets moderators veto a message from the starboard by reacting to it.
+#[derive(Debug, Clone)]
+pub struct StarboardVeto {
+    /// The emoji to react with. Either a unicode emoji, or the name or ID of a custom emoji.
+    pub emoji: String,
+    /// The role a member must have for their reaction to veto the message.
+    pub moderator_role_id: Id<RoleMarker>,
+}
 
 #[derive(Debug)]
 pub struct ApplicationConfig {
@@ -16,19 +181,33 @@ pub struct ApplicationConfig {
     pub discord_token: String,
     /// The URL of the database server to connect to or create, if it does not exist.
     pub database_url: String,
-    /// The amount of unique reactions (not including message author) to a message to make it starboard material.
-    pub reaction_requirement: u32,
-    /// The channel to post starboard messages into
-    pub starboard_channel_id: Id<ChannelMarker>,
-    /// The announcement RSS URLs to read from, paired with the channel ID to post to. Also includes an optional
-    /// role that can be pinged when announcements are made.
+    /// The starboards to post messages into.
+    pub starboards: Vec<Starboard>,
+    /// How reactions are counted towards the `reaction_requirement` of each starboard.
+    pub counting_mode: CountingMode,
+    /// How to handle the starboard post of a message that was deleted.
+    pub starboard_deleted_message_policy: DeletedMessagePolicy,
+    /// The reaction moderators use to veto a message from the starboard, if enabled.
+    pub starboard_veto: Option<StarboardVeto>,
+    /// The announcement RSS feeds to read from, each with the channel to post to and the role that can be
+    /// pinged when announcements are made. These are only stored as the initial feeds, which are then
+    /// managed with the `/feed` command.
     ///
     /// This is an optional feature, and the user may not specify it.
-    pub announcement_rss_urls: Option<Vec<ParsedRssUrl>>,
-    /// The amount of time (in seconds) to wait before performing checking operations for new announcements.
+    pub announcement_rss_urls: Option<Vec<AnnouncementFeed>>,
+    /// The amount of time (in seconds) to wait before performing checking operations for new announcements,
+    /// for feeds that do not specify their own interval.
     pub announcement_check_interval: Duration,
+    /// The most announcement feeds that are fetched at the same time.
+    pub announcement_fetch_concurrency: usize,
     /// The server to only track messages in, if specified.
     pub server_id: Option<Id<GuildMarker>>,
+    /// The channel that persistent failures of background tasks are reported to, if specified.
+    pub admin_channel_id: Option<Id<ChannelMarker>>,
+    /// The most gateway events that are handled at the same time.
+    pub event_concurrency: usize,
+    /// The timezone the times of daily announcement digests are in.
+    pub digest_timezone: Tz,
 }
 
 /// Loads the specified environment variable, returning `Ok` with the env variable if found, or `Err` if it was not found.
@@ -40,28 +219,189 @@ fn load_env(env_var: &str) -> Result<String, Report<ConfigError>> {
     Ok(variable)
 }
 
+/// Parses a single line of the `STARBOARDS` environment variable.
+///
+/// Each line is formatted as
+/// `name,channel_id,reaction_requirement[,emojis[,channels[,removal_threshold]]]`, where `emojis` and
+/// `channels` are space separated lists. Channels prefixed with `-` are excluded from the board.
+fn parse_starboard(line: &str) -> Result<Starboard, Report<ConfigError>> {
+    let parse_error = || ConfigError::ParseError {
+        config_option: "STARBOARDS".to_string(),
+    };
+    let parse_channel_id = |channel_id: &str| {
+        channel_id
+            .parse::<u64>()
+            .ok()
+            .and_then(Id::new_checked)
+            .ok_or_else(parse_error)
+    };
+
+    let mut parts = line.split(',').map(str::trim);
+    let name = parts
+        .next()
+        .filter(|name| !name.is_empty())
+        .ok_or_else(parse_error)?
+        .to_string();
+    let channel_id = parse_channel_id(parts.next().ok_or_else(parse_error)?)?;
+    let reaction_requirement = parts
+        .next()
+        .ok_or_else(parse_error)?
+        .parse::<u32>()
+        .change_context_lazy(parse_error)?;
+    let emojis = parts
+        .next()
+        .map(|emojis| emojis.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+
+    let mut include_channels = Vec::new();
+    let mut exclude_channels = Vec::new();
+    for channel in parts.next().unwrap_or_default().split_whitespace() {
+        match channel.strip_prefix('-') {
+            Some(channel) => exclude_channels.push(parse_channel_id(channel)?),
+            None => include_channels.push(parse_channel_id(channel)?),
+        }
+    }
+
+    let removal_threshold = parts
+        .next()
+        .filter(|threshold| !threshold.is_empty())
+        .map(|threshold| threshold.parse::<u32>())
+        .transpose()
+        .change_context_lazy(parse_error)?;
+
+    Ok(Starboard {
+        name,
+        channel_id,
+        reaction_requirement,
+        removal_threshold,
+        emojis,
+        include_channels,
+        exclude_channels,
+    })
+}
+
+/// Loads the single starboard used when `STARBOARDS` is not specified, counting the `emojis` given.
+fn load_default_starboard(emojis: Vec<String>) -> Result<Starboard, Report<ConfigError>> {
+    let reaction_requirement = load_env("REACTION_REQUIREMENT")?
+        .parse::<u32>()
+        .change_context(ConfigError::ParseError {
+            config_option: "REACTION_REQUIREMENT".to_string(),
+        })?;
+    let removal_threshold = load_env("STARBOARD_REMOVAL_THRESHOLD")
+        .ok()
+        .map(|threshold| threshold.parse::<u32>())
+        .transpose()
+        .change_context(ConfigError::ParseError {
+            config_option: "STARBOARD_REMOVAL_THRESHOLD".to_string(),
+        })?;
+    let channel_id = Id::new(
+        load_env("STARBOARD_CHANNEL_ID")?
+            .parse::<u64>()
+            .change_context(ConfigError::ParseError {
+                config_option: "STARBOARD_CHANNEL_ID".to_string(),
+            })?,
+    );
+
+    Ok(Starboard {
+        name: DEFAULT_STARBOARD_NAME.to_string(),
+        channel_id,
+        reaction_requirement,
+        removal_threshold,
+        emojis,
+        include_channels: vec![],
+        exclude_channels: vec![],
+    })
+}
+
 impl ApplicationConfig {
     /// Loads all environment variables, returning `Err` if one was missing.
     pub fn load() -> Result<Self, Report<ConfigError>> {
         let discord_token = load_env("DISCORD_TOKEN")?;
         let database_url = load_env("DATABASE_URL")?;
-        let reaction_requirement = load_env("REACTION_REQUIREMENT")?
-            .parse::<u32>()
-            .change_context(ConfigError::ParseError {
-                config_option: "REACTION_REQUIREMENT".to_string(),
-            })?;
-        let starboard_channel_id = Id::new(
-            load_env("STARBOARD_CHANNEL_ID")?
-                .parse::<u64>()
-                .change_context(ConfigError::ParseError {
-                    config_option: "STARBOARD_CHANNEL_ID".to_string(),
-                })?,
-        );
+        let (counting_mode, default_emojis) =
+            match load_env("STARBOARD_COUNTING_MODE").ok().as_deref() {
+                None | Some("single_emoji") => (CountingMode::SingleEmoji, vec![]),
+                Some("unique_users") => (CountingMode::UniqueUsers, vec![]),
+                // only counting a specific emoji is the same as a board with just that emoji
+                Some(mode) => match mode.strip_prefix("emoji:") {
+                    Some(emoji) if !emoji.is_empty() => {
+                        (CountingMode::SingleEmoji, vec![emoji.to_string()])
+                    }
+                    _ => {
+                        return Err(Report::new(ConfigError::ParseError {
+                            config_option: "STARBOARD_COUNTING_MODE".to_string(),
+                        }))
+                    }
+                },
+            };
+        let starboards = match load_env("STARBOARDS").ok() {
+            Some(starboards) => {
+                let starboards = starboards
+                    .split('\n')
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(parse_starboard)
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // each board must have a unique name, as posts are remembered by board name
+                let mut names = starboards
+                    .iter()
+                    .map(|board| &board.name)
+                    .collect::<Vec<_>>();
+                names.sort();
+                names.dedup();
+                if names.len() != starboards.len() {
+                    return Err(Report::new(ConfigError::ParseError {
+                        config_option: "STARBOARDS".to_string(),
+                    }));
+                }
+
+                // boards specify the emojis they count themselves
+                if !default_emojis.is_empty() {
+                    return Err(Report::new(ConfigError::ParseError {
+                        config_option: "STARBOARD_COUNTING_MODE".to_string(),
+                    }));
+                }
+
+                starboards
+            }
+            // without any boards specified, fallback to a single board
+            None => vec![load_default_starboard(default_emojis)?],
+        };
+        let starboard_deleted_message_policy =
+            match load_env("STARBOARD_DELETED_MESSAGE_POLICY").ok().as_deref() {
+                None | Some("delete") => DeletedMessagePolicy::Delete,
+                Some("tombstone") => DeletedMessagePolicy::Tombstone,
+                Some(_) => {
+                    return Err(Report::new(ConfigError::ParseError {
+                        config_option: "STARBOARD_DELETED_MESSAGE_POLICY".to_string(),
+                    }))
+                }
+            };
+        // vetoing is only enabled once an emoji is given, which then requires a moderator role
+        let starboard_veto = load_env("STARBOARD_VETO_EMOJI")
+            .ok()
+            .map(|emoji| -> Result<StarboardVeto, Report<ConfigError>> {
+                let moderator_role_id = load_env("STARBOARD_MODERATOR_ROLE_ID")?
+                    .parse::<u64>()
+                    .change_context(ConfigError::ParseError {
+                        config_option: "STARBOARD_MODERATOR_ROLE_ID".to_string(),
+                    })?;
+
+                Ok(StarboardVeto {
+                    emoji,
+                    moderator_role_id: Id::new(moderator_role_id),
+                })
+            })
+            .transpose()?;
+        // feeds are checked on a timer, which needs a period
         let announcement_check_interval = load_env("ANNOUNCEMENT_CHECK_INTERVAL")?
             .parse::<u64>()
-            .change_context(ConfigError::ParseError {
-            config_option: "ANNOUNCEMENT_CHECK_INTERVAL".to_string(),
-        })?;
+            .ok()
+            .filter(|interval| *interval > 0)
+            .ok_or(ConfigError::ParseError {
+                config_option: "ANNOUNCEMENT_CHECK_INTERVAL".to_string(),
+            })?;
         let announcement_check_interval = Duration::from_secs(announcement_check_interval);
 
         // since this is an optional feature, if it didn't exist, then no problem
@@ -73,17 +413,19 @@ impl ApplicationConfig {
                     .filter_map(|line| {
                         // we only want to take valid lines
                         // lines that don't pass our parsing will be ignored
-                        // we parse into the `ParsedRssUrl` type
+                        // we parse into the `AnnouncementFeed` type
                         let mut parts = line.split(',');
                         let rss_url = parts.next();
                         let channel_id = parts.next();
-                        let role_id = parts.next();
+                        // an empty role lets the interval be given without pinging anyone
+                        let role_id = parts.next().filter(|role_id| !role_id.is_empty());
+                        let check_interval = parts.next();
 
                         rss_url
                             .zip(channel_id)
-                            .map(|(url, channel_id)| (url, channel_id, role_id))
+                            .map(|(url, channel_id)| (url, channel_id, role_id, check_interval))
                     }) // remove invalid lines
-                    .map(|(rss, channel_id, role_id)| -> Result<Option<ParsedRssUrl>, Report<ConfigError>> {
+                    .map(|(rss, channel_id, role_id, check_interval)| -> Result<Option<AnnouncementFeed>, Report<ConfigError>> {
                         // attempt to parse the channel id and create channel marker
                         let channel_id =
                             channel_id
@@ -105,7 +447,29 @@ impl ApplicationConfig {
                             .transpose()?;
                         let role_marker = role_id.map(Id::new);
 
-                        Ok(Some((rss.to_string(), channel_marker, role_marker)))
+                        let check_interval = check_interval
+                            .map(|check_interval| {
+                                check_interval
+                                    .parse::<u64>()
+                                    .ok()
+                                    .filter(|interval| *interval > 0)
+                                    .ok_or(ConfigError::ParseError {
+                                        config_option: "ANNOUNCEMENT_CHECK_INTERVAL".to_string(),
+                                    })
+                            })
+                            .transpose()?
+                            .map(Duration::from_secs);
+
+                        Ok(Some(AnnouncementFeed {
+                            url: rss.to_string(),
+                            channel_id: channel_marker,
+                            role_id: role_marker,
+                            label: None,
+                            check_interval,
+                            thread_archive_duration: None,
+                            webhook: None,
+                            digest: None,
+                        }))
                     })
                     .collect::<Result<Vec<_>, _>>()
             })
@@ -113,6 +477,16 @@ impl ApplicationConfig {
             .transpose()?
             // turn our Option<Vec<Option<...>>> into a Option<Vec<...>>
             .map(|urls| urls.into_iter().flatten().collect());
+        let announcement_fetch_concurrency = match load_env("ANNOUNCEMENT_FETCH_CONCURRENCY").ok() {
+            Some(concurrency) => concurrency
+                .parse::<usize>()
+                .ok()
+                .filter(|concurrency| *concurrency > 0)
+                .ok_or(ConfigError::ParseError {
+                    config_option: "ANNOUNCEMENT_FETCH_CONCURRENCY".to_string(),
+                })?,
+            None => DEFAULT_ANNOUNCEMENT_FETCH_CONCURRENCY,
+        };
 
         let server_id = load_env("SERVER_ID")
             .ok()
@@ -123,14 +497,183 @@ impl ApplicationConfig {
             })?
             .map(Id::new);
 
+        let admin_channel_id = load_env("ADMIN_CHANNEL_ID")
+            .ok()
+            .map(|channel_id| channel_id.parse::<u64>())
+            .transpose()
+            .change_context::<ConfigError>(ConfigError::ParseError {
+                config_option: "ADMIN_CHANNEL_ID".to_string(),
+            })?
+            .map(Id::new);
+        let event_concurrency = match load_env("EVENT_CONCURRENCY").ok() {
+            Some(concurrency) => concurrency
+                .parse::<usize>()
+                .ok()
+                .filter(|concurrency| *concurrency > 0)
+                .ok_or(ConfigError::ParseError {
+                    config_option: "EVENT_CONCURRENCY".to_string(),
+                })?,
+            None => DEFAULT_EVENT_CONCURRENCY,
+        };
+        let digest_timezone = match load_env("DIGEST_TIMEZONE").ok() {
+            Some(timezone) => {
+                timezone
+                    .trim()
+                    .parse::<Tz>()
+                    .ok()
+                    .ok_or(ConfigError::ParseError {
+                        config_option: "DIGEST_TIMEZONE".to_string(),
+                    })?
+            }
+            None => Tz::UTC,
+        };
+
         Ok(Self {
             database_url,
             discord_token,
-            reaction_requirement,
-            starboard_channel_id,
+            starboards,
+            counting_mode,
+            starboard_deleted_message_policy,
+            starboard_veto,
             announcement_rss_urls,
             announcement_check_interval,
+            announcement_fetch_concurrency,
             server_id,
+            admin_channel_id,
+            event_concurrency,
+            digest_timezone,
         })
     }
+
+    /// Finds the starboard with the given `name`.
+    pub fn starboard(&self, name: &str) -> Option<&Starboard> {
+        self.starboards.iter().find(|board| board.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        time.parse().expect("valid time")
+    }
+
+    fn daily(time: &str) -> DigestSchedule {
+        DigestSchedule::parse(&format!("daily {time}")).expect("valid schedule")
+    }
+
+    #[test]
+    fn parses_schedules() {
+        assert_eq!(
+            DigestSchedule::parse("hourly"),
+            Some(DigestSchedule::Hourly)
+        );
+        assert_eq!(
+            DigestSchedule::parse(" Hourly "),
+            Some(DigestSchedule::Hourly)
+        );
+        assert_eq!(
+            DigestSchedule::parse("daily 08:30"),
+            Some(DigestSchedule::Daily {
+                time: NaiveTime::from_hms_opt(8, 30, 0).unwrap()
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        for schedule in [
+            "",
+            "weekly",
+            "daily",
+            "daily 25:00",
+            "daily 8am",
+            "hourly 08:00",
+            "daily 08:00 09:00",
+        ] {
+            assert_eq!(DigestSchedule::parse(schedule), None, "{schedule}");
+        }
+    }
+
+    #[test]
+    fn schedules_are_stored_as_they_are_parsed() {
+        for schedule in ["hourly", "daily 08:00", "daily 23:59"] {
+            assert_eq!(
+                DigestSchedule::parse(schedule).unwrap().to_string(),
+                schedule
+            );
+        }
+    }
+
+    #[test]
+    fn hourly_digests_are_at_the_start_of_the_next_hour() {
+        let hourly = DigestSchedule::Hourly;
+
+        assert_eq!(
+            hourly.next_time(utc("2026-10-17T10:15:00Z"), Tz::UTC),
+            utc("2026-10-17T11:00:00Z")
+        );
+        // a digest that is due now has already been posted
+        assert_eq!(
+            hourly.next_time(utc("2026-10-17T11:00:00Z"), Tz::UTC),
+            utc("2026-10-17T12:00:00Z")
+        );
+        assert_eq!(
+            hourly.next_time(utc("2026-12-31T23:30:00Z"), Tz::UTC),
+            utc("2027-01-01T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn hourly_digests_follow_the_hours_of_the_timezone() {
+        // India is 5 hours and 30 minutes ahead of UTC
+        assert_eq!(
+            DigestSchedule::Hourly.next_time(utc("2026-10-17T10:15:00Z"), Tz::Asia__Kolkata),
+            utc("2026-10-17T10:30:00Z")
+        );
+    }
+
+    #[test]
+    fn daily_digests_are_at_the_next_occurrence_of_their_time() {
+        let schedule = daily("08:00");
+
+        assert_eq!(
+            schedule.next_time(utc("2026-10-17T07:00:00Z"), Tz::UTC),
+            utc("2026-10-17T08:00:00Z")
+        );
+        assert_eq!(
+            schedule.next_time(utc("2026-10-17T08:00:00Z"), Tz::UTC),
+            utc("2026-10-18T08:00:00Z")
+        );
+        // New York is 4 hours behind UTC in the summer
+        assert_eq!(
+            schedule.next_time(utc("2026-07-01T13:00:00Z"), Tz::America__New_York),
+            utc("2026-07-02T12:00:00Z")
+        );
+    }
+
+    #[test]
+    fn daily_digests_skipped_by_daylight_saving_are_posted_after_the_change() {
+        // clocks in New York went from 02:00 to 03:00 on 8 March 2026
+        assert_eq!(
+            daily("02:30").next_time(utc("2026-03-08T05:00:00Z"), Tz::America__New_York),
+            utc("2026-03-08T07:30:00Z")
+        );
+    }
+
+    #[test]
+    fn daily_digests_repeated_by_daylight_saving_are_posted_once() {
+        // clocks in New York went from 02:00 back to 01:00 on 1 November 2026
+        let schedule = daily("01:30");
+
+        assert_eq!(
+            schedule.next_time(utc("2026-11-01T04:00:00Z"), Tz::America__New_York),
+            utc("2026-11-01T05:30:00Z")
+        );
+        assert_eq!(
+            schedule.next_time(utc("2026-11-01T05:30:00Z"), Tz::America__New_York),
+            utc("2026-11-02T06:30:00Z")
+        );
+    }
 }
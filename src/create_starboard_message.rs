@@ -1,9 +1,21 @@
-use twilight_model::channel::{
-    message::{
-        embed::{EmbedAuthor, EmbedField, EmbedImage},
-        Embed, ReactionType,
+use twilight_model::{
+    channel::{
+        message::{
+            embed::{EmbedAuthor, EmbedField, EmbedImage},
+            sticker::{MessageSticker, StickerFormatType},
+            Embed, ReactionType,
+        },
+        Attachment, Message,
+    },
+    id::{
+        marker::{ChannelMarker, GuildMarker, MessageMarker},
+        Id,
     },
-    Message,
+};
+
+use crate::{
+    markdown::{join_lines, truncate_markdown},
+    starboard::ReactionTally,
 };
 
 /// A struct that contains the relevant information to pass to an [`twilight_http::request::channel::message::UpdateMessage`]
@@ -15,35 +27,217 @@ pub struct StarboardMessage {
     pub embeds: Vec<Embed>,
 }
 
+/// Formats an emoji so that it renders in a Discord message.
+pub fn format_emoji(emoji: &ReactionType) -> String {
+    match emoji {
+        ReactionType::Unicode { name } => name.to_owned(),
+        ReactionType::Custom { id, name, .. } => {
+            format!("<:{}:{id}>", name.as_deref().unwrap_or_default())
+        }
+    }
+}
+
+/// Creates a link that jumps to a message when clicked.
+pub fn message_link(
+    guild_id: Option<Id<GuildMarker>>,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+) -> String {
+    format!(
+        "https://discord.com/channels/{}/{channel_id}/{message_id}",
+        match guild_id {
+            Some(guild_id) => guild_id.to_string(),
+            None => "@me".to_string(),
+        }
+    )
+}
+
+/// The most images Discord shows together as a gallery on a single message.
+const MAX_GALLERY_IMAGES: usize = 4;
+
+/// The most characters Discord allows in the value of an embed field.
+const MAX_FIELD_LENGTH: usize = 1024;
+
+/// The most characters of a replied to message quoted in a starboard post.
+const MAX_REPLY_LENGTH: usize = 200;
+
+/// An image shown on a starboard post.
+struct Image {
+    url: String,
+    proxy_url: Option<String>,
+}
+
+/// Whether an attachment is an image that can be shown in an embed.
+fn is_image(attachment: &Attachment) -> bool {
+    match &attachment.content_type {
+        Some(content_type) => content_type.starts_with("image/"),
+        // older attachments do not have a content type, so fallback to the file extension
+        None => {
+            let filename = attachment.filename.to_lowercase();
+            [".png", ".jpg", ".jpeg", ".gif", ".webp"]
+                .iter()
+                .any(|extension| filename.ends_with(extension))
+        }
+    }
+}
+
+/// The URL of the image of a sticker, if it is in a format that can be shown in an embed.
+fn sticker_url(sticker: &MessageSticker) -> Option<String> {
+    let extension = match sticker.format_type {
+        StickerFormatType::Png | StickerFormatType::Apng => "png",
+        StickerFormatType::Gif => "gif",
+        _ => return None,
+    };
+
+    Some(format!(
+        "https://media.discordapp.net/stickers/{}.{extension}",
+        sticker.id
+    ))
+}
+
+/// The image of the first embed of the message that has one, such as the preview of a linked gif
+/// or video.
+fn embed_image(message: &Message) -> Option<Image> {
+    message.embeds.iter().find_map(|embed| {
+        let image = embed
+            .image
+            .as_ref()
+            .map(|image| (&image.url, &image.proxy_url))
+            .or_else(|| {
+                embed
+                    .thumbnail
+                    .as_ref()
+                    .map(|thumbnail| (&thumbnail.url, &thumbnail.proxy_url))
+            });
+
+        image.map(|(url, proxy_url)| Image {
+            url: url.clone(),
+            proxy_url: proxy_url.clone(),
+        })
+    })
+}
+
 /// Generates the relevant fields to set in a [`twilight_http::request::channel::message::UpdateMessage`]
 /// or [`twilight_http::request::channel::message::CreateMessage`] struct to represent a starboard message.
-pub fn create_starboard_message(message: Message) -> StarboardMessage {
-    let max_reactions = message
-        .reactions
+///
+/// Images attached to the message are shown as a gallery, while other attachments, such as videos,
+/// are linked to. Without any attached images, the message's sticker or the image of its first
+/// embed is shown instead. Replies quote the message they are replying to.
+pub fn create_starboard_message(message: &Message, tally: &ReactionTally) -> StarboardMessage {
+    // a message may have had all of its reactions cleared since it was starboard'd
+    let content = match &tally.emoji {
+        Some(emoji) => format!(
+            "{} {} in <#{}>",
+            tally.count,
+            format_emoji(emoji),
+            message.channel_id
+        ),
+        None => format!("No reactions in <#{}>", message.channel_id),
+    };
+
+    let link = message_link(message.guild_id, message.channel_id, message.id);
+    let mut fields = vec![];
+
+    // quote the message being replied to, so the post makes sense on its own
+    if let Some(replied) = &message.referenced_message {
+        let quote = match replied.content.is_empty() {
+            true => "*No text content*".to_string(),
+            false => truncate_markdown(&replied.content, MAX_REPLY_LENGTH)
+                .lines()
+                .map(|line| format!("> {line}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+
+        fields.push(EmbedField {
+            inline: false,
+            name: format!("Replying to {}", replied.author.name),
+            value: truncate_markdown(
+                &format!(
+                    "{quote}\n[Jump to reply]({})",
+                    message_link(message.guild_id, replied.channel_id, replied.id)
+                ),
+                MAX_FIELD_LENGTH,
+            ),
+        });
+    }
+
+    let (images, files): (Vec<_>, Vec<_>) = message
+        .attachments
         .iter()
-        .reduce(|current_max_reaction, reaction| {
-            if reaction.count > current_max_reaction.count {
-                reaction
-            } else {
-                current_max_reaction
-            }
+        .partition(|attachment| is_image(attachment));
+
+    let mut images = images
+        .into_iter()
+        .map(|attachment| Image {
+            url: attachment.url.clone(),
+            proxy_url: Some(attachment.proxy_url.clone()),
         })
-        .expect("Call to create_starboard_message with a message that has no reactions");
-
-    let content = format!(
-        "{} {} in <#{}>",
-        max_reactions.count,
-        match &max_reactions.emoji {
-            ReactionType::Unicode { name } => name.to_owned(),
-            ReactionType::Custom { id, name, .. } =>
-                format!("<:{}:{id}>", name.as_deref().unwrap_or_default()),
-        },
-        message.channel_id
-    );
+        .collect::<Vec<_>>();
+
+    // images that do not fit in the gallery are linked to instead
+    let mut files = files
+        .into_iter()
+        .chain(
+            message
+                .attachments
+                .iter()
+                .filter(|attachment| is_image(attachment))
+                .skip(MAX_GALLERY_IMAGES),
+        )
+        .map(|attachment| format!("[{}]({})", attachment.filename, attachment.url))
+        .collect::<Vec<_>>();
+    images.truncate(MAX_GALLERY_IMAGES);
+
+    // stickers that can not be shown as an image are named instead
+    let mut stickers = vec![];
+    for sticker in &message.sticker_items {
+        match sticker_url(sticker) {
+            Some(url) if images.is_empty() => images.push(Image {
+                url,
+                proxy_url: None,
+            }),
+            Some(url) => files.push(format!("[{}]({url})", sticker.name)),
+            None => stickers.push(sticker.name.clone()),
+        }
+    }
+
+    if images.is_empty() {
+        images.extend(embed_image(message));
+    }
+
+    if !files.is_empty() {
+        fields.push(EmbedField {
+            inline: false,
+            name: "Attachments".to_string(),
+            value: join_lines(&files, MAX_FIELD_LENGTH),
+        });
+    }
+
+    if !stickers.is_empty() {
+        fields.push(EmbedField {
+            inline: false,
+            name: "Stickers".to_string(),
+            value: join_lines(&stickers, MAX_FIELD_LENGTH),
+        });
+    }
+
+    fields.push(EmbedField {
+        inline: false,
+        name: "Message Link".to_string(),
+        value: format!("[Click to jump to message]({link})"),
+    });
 
-    let embeds = vec![Embed {
+    let mut images = images.into_iter().map(|image| EmbedImage {
+        url: image.url,
+        proxy_url: image.proxy_url,
+        height: None,
+        width: None,
+    });
+
+    let mut embeds = vec![Embed {
         author: Some(EmbedAuthor {
-            icon_url: Some(match message.author.avatar {
+            icon_url: Some(match &message.author.avatar {
                 Some(hash) => format!(
                     "https://cdn.discordapp.com/avatars/{}/{}.{}",
                     message.author.id,
@@ -55,40 +249,62 @@ pub fn create_starboard_message(message: Message) -> StarboardMessage {
                     message.author.discriminator % 5
                 ),
             }),
-            name: message.author.name,
+            name: message.author.name.clone(),
             proxy_icon_url: None,
             url: None,
         }),
         color: Some(15844367),
-        description: Some(message.content),
-        fields: vec![EmbedField {
-            inline: false,
-            name: "Message Link".to_string(),
-            value: format!(
-                "[Click to jump to message](https://discord.com/channels/{}/{}/{})",
-                match message.guild_id {
-                    Some(guild_id) => guild_id.to_string(),
-                    None => "@me".to_string(),
-                },
-                message.channel_id,
-                message.id
-            ),
-        }],
+        description: Some(message.content.clone()),
+        fields,
         footer: None,
         timestamp: Some(message.timestamp),
         kind: "rich".to_string(),
-        image: message.attachments.into_iter().next().map(|i| EmbedImage {
-            url: i.url,
-            proxy_url: Some(i.proxy_url),
-            height: None,
-            width: None,
-        }),
+        image: images.next(),
         provider: None,
         thumbnail: None,
         title: None,
-        url: None,
+        // embeds sharing a URL are shown together, which turns the extra images into a gallery
+        url: Some(link.clone()),
         video: None,
     }];
 
+    embeds.extend(images.map(|image| Embed {
+        author: None,
+        color: Some(15844367),
+        description: None,
+        fields: vec![],
+        footer: None,
+        timestamp: None,
+        kind: "rich".to_string(),
+        image: Some(image),
+        provider: None,
+        thumbnail: None,
+        title: None,
+        url: Some(link.clone()),
+        video: None,
+    }));
+
     StarboardMessage { content, embeds }
 }
+
+/// Generates the starboard message that replaces the starboard post of a message which has been deleted.
+pub fn create_deleted_starboard_message(channel_id: Id<ChannelMarker>) -> StarboardMessage {
+    StarboardMessage {
+        content: format!("Deleted message in <#{channel_id}>"),
+        embeds: vec![Embed {
+            author: None,
+            color: Some(15844367),
+            description: Some("*This message has been deleted.*".to_string()),
+            fields: vec![],
+            footer: None,
+            timestamp: None,
+            kind: "rich".to_string(),
+            image: None,
+            provider: None,
+            thumbnail: None,
+            title: None,
+            url: None,
+            video: None,
+        }],
+    }
+}
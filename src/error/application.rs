@@ -11,8 +11,6 @@ pub enum ApplicationError {
     LoadConfig,
     Database(DatabaseError),
     Discord(DiscordError),
-    Event,
-    Thread,
 }
 
 impl Error for ApplicationError {}
@@ -25,12 +23,22 @@ impl fmt::Display for ApplicationError {
             }
             ApplicationError::Database(database_error) => match database_error {
                 DatabaseError::ConnectError => write!(f, "Failed when connecting to database"),
+                DatabaseError::ResumeBackfill => {
+                    write!(f, "Failed to resume interrupted starboard backfills")
+                }
+                DatabaseError::SeedFeeds => {
+                    write!(
+                        f,
+                        "Failed to store the announcement feeds from the environment"
+                    )
+                }
             },
             ApplicationError::Discord(discord_error) => match discord_error {
                 DiscordError::ConnectError => write!(f, "Failed to start Discord bot"),
+                DiscordError::RegisterCommands => {
+                    write!(f, "Failed to register application commands")
+                }
             },
-            ApplicationError::Event => write!(f, "Failed to process event"),
-            ApplicationError::Thread => write!(f, "Failed to handle tokio thread unwinding"),
         }
     }
 }
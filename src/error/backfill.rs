@@ -0,0 +1,28 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Debug)]
+pub enum BackfillError {
+    /// Failed to read or record the progress of the backfill.
+    Progress,
+    /// Failed to retrieve the history of the channel.
+    RetrieveMessages,
+    /// Failed to post a message from the history to the starboard.
+    Starboard,
+}
+
+impl Display for BackfillError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let backfill_error = match self {
+            BackfillError::Progress => "Failed to access the progress of the backfill",
+            BackfillError::RetrieveMessages => "Failed to retrieve the history of the channel",
+            BackfillError::Starboard => "Failed to post a message from history to the starboard",
+        };
+
+        write!(f, "{backfill_error}")
+    }
+}
+
+impl Error for BackfillError {}
@@ -0,0 +1,25 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Debug)]
+pub enum BlocklistError {
+    /// Failed to read or update the blocklist.
+    Database,
+    /// Failed to remove the starboard posts of a vetoed message.
+    RemovePosts,
+}
+
+impl Display for BlocklistError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let blocklist_error = match self {
+            BlocklistError::Database => "Failed to access the starboard blocklist",
+            BlocklistError::RemovePosts => "Failed to remove the starboard posts of the message",
+        };
+
+        write!(f, "{blocklist_error}")
+    }
+}
+
+impl Error for BlocklistError {}
@@ -0,0 +1,37 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Debug)]
+pub enum CommandError {
+    /// Failed to register the application commands with Discord.
+    Register,
+    /// The command invoked is not one the bot provides.
+    UnknownCommand,
+    /// The options given to the command were missing or invalid.
+    InvalidOptions,
+    /// Failed to access the database.
+    Database,
+    /// Failed to update the posts on the starboard.
+    Starboard,
+    /// Failed to read an announcement feed.
+    Feed,
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let command_error = match self {
+            CommandError::Register => "Failed to register application commands",
+            CommandError::UnknownCommand => "The command used is not known",
+            CommandError::InvalidOptions => "The options given to the command were invalid",
+            CommandError::Database => "Failed to access the database",
+            CommandError::Starboard => "Failed to update the starboard",
+            CommandError::Feed => "Failed to read the announcement feed",
+        };
+
+        write!(f, "{command_error}")
+    }
+}
+
+impl Error for CommandError {}
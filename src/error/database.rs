@@ -1,4 +1,6 @@
 #[derive(Debug)]
 pub enum DatabaseError {
     ConnectError,
+    ResumeBackfill,
+    SeedFeeds,
 }
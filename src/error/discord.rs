@@ -1,4 +1,13 @@
+use twilight_http::{error::ErrorType, Error as HttpError};
+
 #[derive(Debug)]
 pub enum DiscordError {
     ConnectError,
+    RegisterCommands,
+}
+
+/// Whether Discord failed a request because what it refers to does not exist, such as a message
+/// that has already been deleted.
+pub fn is_not_found(error: &HttpError) -> bool {
+    matches!(error.kind(), ErrorType::Response { status, .. } if status.get() == 404)
 }
@@ -7,13 +7,22 @@ use std::{
 #[derive(Debug)]
 pub enum EventError {
     /// Failed to handle a message having a reaction event (added / removed).
-    ReactionError,
+    Reaction,
+    /// Failed to handle a message being edited or deleted.
+    Message,
+    /// Failed to handle a channel being deleted.
+    Channel,
+    /// Failed to handle a user interacting with the bot, such as through a command.
+    Interaction,
 }
 
 impl EventError {
     fn get_event_name(&self) -> &'static str {
         match self {
-            EventError::ReactionError => "Reaction",
+            EventError::Reaction => "Reaction",
+            EventError::Message => "Message",
+            EventError::Channel => "Channel",
+            EventError::Interaction => "Interaction",
         }
     }
 }
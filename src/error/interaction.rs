@@ -0,0 +1,25 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Debug)]
+pub enum InteractionError {
+    /// Failed to acknowledge the interaction.
+    Defer,
+    /// Failed to send the response to the interaction.
+    Respond,
+}
+
+impl Display for InteractionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let interaction_error = match self {
+            InteractionError::Defer => "Failed to acknowledge the interaction",
+            InteractionError::Respond => "Failed to respond to the interaction",
+        };
+
+        write!(f, "{interaction_error}")
+    }
+}
+
+impl Error for InteractionError {}
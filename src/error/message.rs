@@ -0,0 +1,28 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Debug)]
+pub enum MessageError {
+    /// Failed to acquire a lock on a database pool.
+    DatabaseConnect,
+    /// Failed to read or update the starboard entry of a message.
+    StarboardEntry,
+    /// Failed to update the starboard post of a message.
+    StarboardMessage,
+}
+
+impl Display for MessageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let event_error = match self {
+            MessageError::DatabaseConnect => "Failed to acquire database pool connection",
+            MessageError::StarboardEntry => "Failed to access the starboard entry of the message",
+            MessageError::StarboardMessage => "Failed to update the starboard message",
+        };
+
+        write!(f, "{event_error}")
+    }
+}
+
+impl Error for MessageError {}
@@ -1,15 +1,25 @@
 mod application;
+mod backfill;
+mod blocklist;
+mod command;
 mod config;
 mod database;
 mod discord;
 mod event;
+mod interaction;
+mod message;
 mod reaction;
 mod rss;
 
 pub use self::rss::RssError;
 pub use application::ApplicationError;
+pub use backfill::BackfillError;
+pub use blocklist::BlocklistError;
+pub use command::CommandError;
 pub use config::ConfigError;
 pub use database::DatabaseError;
-pub use discord::DiscordError;
+pub use discord::{is_not_found, DiscordError};
 pub use event::EventError;
+pub use interaction::InteractionError;
+pub use message::MessageError;
 pub use reaction::ReactionError;
@@ -11,10 +11,20 @@ pub enum ReactionError {
     PreviousReactionCount,
     /// Failed to retrieve the message reacted to.
     RetrieveMessage,
+    /// Failed to retrieve the users that reacted to the message.
+    ReactionUsers,
+    /// Failed to retrieve the channel the message was sent in.
+    RetrieveChannel,
     /// The response message was too long.
     ContentResponseTooLong,
     /// Failed to generate starboard message
     StarboardMessage,
+    /// Failed to withdraw a starboard message.
+    RemoveStarboardMessage,
+    /// Failed to check whether the message is blocked from the starboard.
+    Blocklist,
+    /// Failed to veto the message from the starboard.
+    Veto,
 }
 
 impl Display for ReactionError {
@@ -25,8 +35,13 @@ impl Display for ReactionError {
                 "Failed to retrieve the previous reaction count"
             }
             ReactionError::RetrieveMessage => "Failed to retrieve the message reacted to",
+            ReactionError::ReactionUsers => "Failed to retrieve the users reacted to the message",
+            ReactionError::RetrieveChannel => "Failed to retrieve the channel of the message",
             ReactionError::ContentResponseTooLong => "Response message exceeded maximum length",
             ReactionError::StarboardMessage => "Failed to create starboard message",
+            ReactionError::RemoveStarboardMessage => "Failed to remove starboard message",
+            ReactionError::Blocklist => "Failed to check the starboard blocklist",
+            ReactionError::Veto => "Failed to veto the message from the starboard",
         };
 
         write!(f, "{event_error}")
@@ -10,6 +10,14 @@ pub enum RssError {
     Database,
     // Failed to post an announcement to Discord.
     Post,
+    // Failed to edit a posted announcement on Discord.
+    Edit,
+    // A task checking a feed panicked or was cancelled.
+    Task,
+    // A filter rule of a feed could not be understood.
+    Filter,
+    // Failed to find or create the webhook to post announcements with.
+    Webhook,
 }
 
 impl Display for RssError {
@@ -19,6 +27,10 @@ impl Display for RssError {
             Self::Read => write!(f, "Failed to decode RSS response from Canvas server"),
             Self::Database => write!(f, "Failed to process database event"),
             Self::Post => write!(f, "Failed to post an RSS event to the Discord channel"),
+            Self::Edit => write!(f, "Failed to edit an RSS event in the Discord channel"),
+            Self::Task => write!(f, "Failed to join the task checking an RSS feed"),
+            Self::Filter => write!(f, "Failed to apply a filter rule of an RSS feed"),
+            Self::Webhook => write!(f, "Failed to set up the webhook to post RSS events with"),
         }
     }
 }
@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use error_stack::{Report, ResultExt};
+use sqlx::SqlitePool;
+use twilight_http::Client;
+use twilight_model::{
+    gateway::payload::incoming::ChannelDelete,
+    id::{marker::MessageMarker, Id},
+};
+
+use crate::{config::ApplicationConfig, error::MessageError, starboard::withdraw_starboard};
+
+/// Fired when a channel is deleted.
+///
+/// Withdraws the starboard post of every starboard'd message that was in the channel. A message that
+/// fails to be withdrawn is logged, without holding up the rest.
+///
+/// Discord removes the threads of a deleted channel without telling us which they were, so messages
+/// starboard'd from those threads keep their posts.
+pub async fn channel_delete(
+    deleted: Box<ChannelDelete>,
+    http: Arc<Client>,
+    pool: SqlitePool,
+    config: Arc<ApplicationConfig>,
+) -> Result<(), Report<MessageError>> {
+    let channel_id = deleted.id.to_string();
+
+    let message_ids = sqlx::query!(
+        r#"
+SELECT DISTINCT message_id
+FROM starboard
+WHERE channel_id = ?
+	"#,
+        channel_id
+    )
+    .fetch_all(&pool)
+    .await
+    .change_context(MessageError::StarboardEntry)?
+    .into_iter()
+    .map(|row| -> std::result::Result<u64, _> { row.message_id.try_into() })
+    .collect::<Result<Vec<_>, _>>()
+    .change_context(MessageError::StarboardEntry)?;
+
+    for message_id in message_ids {
+        let message_id: Id<MessageMarker> = Id::new(message_id);
+        if let Err(report) = withdraw_starboard(deleted.id, message_id, &http, &pool, &config).await
+        {
+            log::error!(
+                "Failed to withdraw the starboard posts of message {message_id}: {report:?}"
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use error_stack::{Report, ResultExt};
+use sqlx::SqlitePool;
+use twilight_http::Client;
+use twilight_model::{
+    application::interaction::InteractionData,
+    channel::message::{AllowedMentions, MessageFlags},
+    gateway::payload::incoming::InteractionCreate,
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+};
+
+use crate::{
+    commands::{self, CommandResponse},
+    config::ApplicationConfig,
+    error::InteractionError,
+};
+
+/// Fired when a user interacts with the bot, such as by using a command.
+///
+/// Acknowledges the interaction straight away, as Discord only waits 3 seconds for a response,
+/// then runs the command and edits its response in.
+pub async fn interaction_create(
+    interaction: Box<InteractionCreate>,
+    http: Arc<Client>,
+    pool: SqlitePool,
+    config: Arc<ApplicationConfig>,
+) -> Result<(), Report<InteractionError>> {
+    let Some(InteractionData::ApplicationCommand(command)) = &interaction.data else {
+        return Ok(());
+    };
+
+    let interaction_client = http.interaction(interaction.application_id);
+    interaction_client
+        .create_response(
+            interaction.id,
+            &interaction.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::DeferredChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    flags: commands::is_ephemeral(&command.name).then_some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            },
+        )
+        .await
+        .change_context(InteractionError::Defer)?;
+
+    let response = commands::handle_command(&interaction, command, http.clone(), pool, config)
+        .await
+        .unwrap_or_else(|report| {
+            log::error!("Failed to run command /{}: {report:?}", command.name);
+            CommandResponse::text(format!(
+                "Something went wrong: {}",
+                report.current_context()
+            ))
+        });
+
+    interaction_client
+        .update_response(&interaction.token)
+        .allowed_mentions(Some(&AllowedMentions::default()))
+        .content(Some(&response.content))
+        .change_context(InteractionError::Respond)?
+        .embeds(Some(&response.embeds))
+        .change_context(InteractionError::Respond)?
+        .await
+        .change_context(InteractionError::Respond)?;
+
+    Ok(())
+}
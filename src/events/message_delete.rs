@@ -0,0 +1,20 @@
+use std::sync::Arc;
+
+use error_stack::Report;
+use sqlx::SqlitePool;
+use twilight_http::Client;
+use twilight_model::gateway::payload::incoming::MessageDelete;
+
+use crate::{config::ApplicationConfig, error::MessageError, starboard::withdraw_starboard};
+
+/// Fired when a message is deleted.
+///
+/// Withdraws the message's starboard post, if it has one.
+pub async fn message_delete(
+    deleted: MessageDelete,
+    http: Arc<Client>,
+    pool: SqlitePool,
+    config: Arc<ApplicationConfig>,
+) -> Result<(), Report<MessageError>> {
+    withdraw_starboard(deleted.channel_id, deleted.id, &http, &pool, &config).await
+}
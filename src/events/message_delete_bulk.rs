@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use error_stack::Report;
+use sqlx::SqlitePool;
+use twilight_http::Client;
+use twilight_model::gateway::payload::incoming::MessageDeleteBulk;
+
+use crate::{config::ApplicationConfig, error::MessageError, starboard::withdraw_starboard};
+
+/// Fired when many messages are deleted at once, such as when a moderator purges a channel.
+///
+/// Withdraws the starboard post of every deleted message that has one. A message that fails to be
+/// withdrawn is logged, without holding up the rest.
+pub async fn message_delete_bulk(
+    deleted: MessageDeleteBulk,
+    http: Arc<Client>,
+    pool: SqlitePool,
+    config: Arc<ApplicationConfig>,
+) -> Result<(), Report<MessageError>> {
+    for message_id in deleted.ids {
+        if let Err(report) =
+            withdraw_starboard(deleted.channel_id, message_id, &http, &pool, &config).await
+        {
+            log::error!(
+                "Failed to withdraw the starboard posts of message {message_id}: {report:?}"
+            );
+        }
+    }
+
+    Ok(())
+}
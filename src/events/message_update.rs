@@ -0,0 +1,22 @@
+use std::sync::Arc;
+
+use error_stack::{Report, ResultExt};
+use sqlx::SqlitePool;
+use twilight_http::Client;
+use twilight_model::gateway::payload::incoming::MessageUpdate;
+
+use crate::{config::ApplicationConfig, error::MessageError, starboard::sync_starboard};
+
+/// Fired when a message is edited.
+///
+/// Re-renders the message's starboard post, if it has one.
+pub async fn message_update(
+    updated: Box<MessageUpdate>,
+    http: Arc<Client>,
+    pool: SqlitePool,
+    config: Arc<ApplicationConfig>,
+) -> Result<(), Report<MessageError>> {
+    sync_starboard(updated.channel_id, updated.id, http, pool, config, false)
+        .await
+        .change_context(MessageError::StarboardMessage)
+}
@@ -1,3 +1,19 @@
+mod channel_delete;
+mod interaction_create;
+mod message_delete;
+mod message_delete_bulk;
+mod message_update;
 mod reaction_add;
+mod reaction_remove;
+mod reaction_remove_all;
+mod reaction_remove_emoji;
 
+pub use channel_delete::channel_delete;
+pub use interaction_create::interaction_create;
+pub use message_delete::message_delete;
+pub use message_delete_bulk::message_delete_bulk;
+pub use message_update::message_update;
 pub use reaction_add::reaction_add;
+pub use reaction_remove::reaction_remove;
+pub use reaction_remove_all::reaction_remove_all;
+pub use reaction_remove_emoji::reaction_remove_emoji;
@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use error_stack::Report;
+use sqlx::SqlitePool;
+use twilight_http::Client;
+use twilight_model::gateway::payload::incoming::ReactionRemove;
+
+use crate::{config::ApplicationConfig, error::ReactionError, starboard::sync_starboard};
+
+/// Fired when a single reaction is removed from a message.
+///
+/// Refreshes the reaction count of the message's starboard post, if it has one.
+pub async fn reaction_remove(
+    removed: Box<ReactionRemove>,
+    http: Arc<Client>,
+    pool: SqlitePool,
+    config: Arc<ApplicationConfig>,
+) -> Result<(), Report<ReactionError>> {
+    // only messages already on the starboard can be affected by a removal, so
+    // there is no need to check the server being tracked here
+    sync_starboard(
+        removed.channel_id,
+        removed.message_id,
+        http,
+        pool,
+        config,
+        false,
+    )
+    .await
+}
@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use error_stack::Report;
+use sqlx::SqlitePool;
+use twilight_http::Client;
+use twilight_model::gateway::payload::incoming::ReactionRemoveAll;
+
+use crate::{config::ApplicationConfig, error::ReactionError, starboard::sync_starboard};
+
+/// Fired when every reaction is cleared from a message.
+///
+/// Refreshes (or withdraws) the message's starboard post, if it has one.
+pub async fn reaction_remove_all(
+    removed: ReactionRemoveAll,
+    http: Arc<Client>,
+    pool: SqlitePool,
+    config: Arc<ApplicationConfig>,
+) -> Result<(), Report<ReactionError>> {
+    sync_starboard(
+        removed.channel_id,
+        removed.message_id,
+        http,
+        pool,
+        config,
+        false,
+    )
+    .await
+}
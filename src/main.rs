@@ -4,20 +4,36 @@ use sqlx::{
     SqlitePool,
 };
 use std::{str::FromStr, sync::Arc};
+use tokio::sync::Semaphore;
 use twilight_cache_inmemory::{InMemoryCache, ResourceType};
 use twilight_gateway::{Event, Intents, Shard, ShardId};
 use twilight_http::Client;
+use twilight_model::channel::message::AllowedMentions;
 
+mod announcement_digest;
+mod announcement_feeds;
+mod announcement_filters;
+mod announcement_media;
+mod announcement_outbox;
+mod announcement_pings;
+mod commands;
 mod config;
 mod create_starboard_message;
 mod error;
 mod events;
+mod markdown;
 mod rss_announcements;
+mod starboard;
+mod supervisor;
 
 use config::ApplicationConfig;
 use error::{ApplicationError, ConfigError, DatabaseError, DiscordError, EventError};
 
-use crate::rss_announcements::handle_announcements;
+use crate::{
+    announcement_digest::post_digests, announcement_feeds::seed_feeds,
+    announcement_outbox::deliver_announcements, rss_announcements::handle_announcements,
+    supervisor::supervise,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Report<ApplicationError>> {
@@ -59,25 +75,82 @@ async fn main() -> Result<(), Report<ApplicationError>> {
             .build(),
     );
 
-    let client = Arc::new(Client::new(config.discord_token.to_owned()));
+    // nobody is pinged unless a message explicitly allows it
+    let client = Arc::new(
+        Client::builder()
+            .token(config.discord_token.to_owned())
+            .default_allowed_mentions(AllowedMentions::default())
+            .build(),
+    );
+
+    commands::register_commands(&client, &config)
+        .await
+        .change_context(ApplicationError::Discord(DiscordError::RegisterCommands))?;
 
-    // if there was announcement urls, spawn up a thread to handle checking it
-    if let Some(announcement_urls) = config.announcement_rss_urls.to_owned() {
-        let check_interval = config.announcement_check_interval;
+    // carry over the announcement urls from the environment, if no feeds have been stored yet
+    if let Some(announcement_feeds) = &config.announcement_rss_urls {
+        seed_feeds(announcement_feeds, &pool)
+            .await
+            .change_context(ApplicationError::Database(DatabaseError::SeedFeeds))?;
+    }
+
+    // spawn up a thread to handle checking the announcement feeds,
+    // which is restarted if it fails
+    {
+        let default_check_interval = config.announcement_check_interval;
+        let fetch_concurrency = config.announcement_fetch_concurrency;
+        let digest_timezone = config.digest_timezone;
         let pool = pool.clone();
-        let client = client.clone();
-
-        tokio::spawn(async move {
-            let result =
-                handle_announcements(announcement_urls, pool, client, check_interval).await;
-            if let Err(report) = result {
-                log::error!("RSS task failed: {report:?}");
-            } else {
-                log::debug!("RSS announcement thread completed with Ok variant");
-            }
-        });
+
+        supervise(
+            "RSS announcement",
+            client.clone(),
+            config.admin_channel_id,
+            move || {
+                handle_announcements(
+                    pool.clone(),
+                    default_check_interval,
+                    fetch_concurrency,
+                    digest_timezone,
+                )
+            },
+        );
     }
 
+    // post the digests of announcements once they are due, through the outbox
+    {
+        let pool = pool.clone();
+
+        supervise(
+            "announcement digest",
+            client.clone(),
+            config.admin_channel_id,
+            move || post_digests(pool.clone()),
+        );
+    }
+
+    // deliver the announcements queued by the feeds, retrying them while Discord is unreachable
+    {
+        let pool = pool.clone();
+        let outbox_client = client.clone();
+
+        supervise(
+            "announcement outbox",
+            client.clone(),
+            config.admin_channel_id,
+            move || deliver_announcements(pool.clone(), outbox_client.clone()),
+        );
+    }
+
+    // continue any starboard backfills that were interrupted by the bot stopping
+    starboard::resume_backfills(client.clone(), pool.clone(), config.clone())
+        .await
+        .change_context(ApplicationError::Database(DatabaseError::ResumeBackfill))?;
+
+    // handle events concurrently, but only so many at once so that a burst of events does not
+    // exhaust the database connections or rate limits
+    let event_permits = Arc::new(Semaphore::new(config.event_concurrency));
+
     // Startup an event loop to process each event in the event stream as they
     // come in.
     loop {
@@ -87,16 +160,31 @@ async fn main() -> Result<(), Report<ApplicationError>> {
                 // Update the cache.
                 cache.update(&event);
 
-                // Spawn a new task to handle the event
-                tokio::spawn(handle_event(
-                    event,
-                    client.clone(),
-                    pool.clone(),
-                    config.clone(),
-                ))
-                .await
-                .change_context(ApplicationError::Thread)?
-                .change_context(ApplicationError::Event)?;
+                let event_permits = event_permits.clone();
+                let http = client.clone();
+                let pool = pool.clone();
+                let config = config.clone();
+
+                // Spawn a new task to handle the event, a failed handler is logged
+                // rather than bringing down the bot
+                tokio::spawn(async move {
+                    // wait for a handler to finish if too many are running, within the task so
+                    // that the gateway keeps being polled. Interactions have to be answered
+                    // within seconds, so they never wait
+                    let _permit = match &event {
+                        Event::InteractionCreate(_) => None,
+                        _ => Some(
+                            event_permits
+                                .acquire_owned()
+                                .await
+                                .expect("event permits are never closed"),
+                        ),
+                    };
+
+                    if let Err(report) = handle_event(event, http, pool, config).await {
+                        log::error!("Failed to handle event: {report:?}");
+                    }
+                });
             }
             Err(source) => {
                 if source.is_fatal() {
@@ -119,7 +207,67 @@ async fn handle_event(
             log::debug!("Received ReactionAdd event to message {}", added.message_id);
             events::reaction_add(added, http, pool, config)
                 .await
-                .change_context(EventError::ReactionError)?;
+                .change_context(EventError::Reaction)?;
+        }
+        Event::ReactionRemove(removed) => {
+            log::debug!(
+                "Received ReactionRemove event to message {}",
+                removed.message_id
+            );
+            events::reaction_remove(removed, http, pool, config)
+                .await
+                .change_context(EventError::Reaction)?;
+        }
+        Event::ReactionRemoveAll(removed) => {
+            log::debug!(
+                "Received ReactionRemoveAll event to message {}",
+                removed.message_id
+            );
+            events::reaction_remove_all(removed, http, pool, config)
+                .await
+                .change_context(EventError::Reaction)?;
+        }
+        Event::ReactionRemoveEmoji(removed) => {
+            log::debug!(
+                "Received ReactionRemoveEmoji event to message {}",
+                removed.message_id
+            );
+            events::reaction_remove_emoji(removed, http, pool, config)
+                .await
+                .change_context(EventError::Reaction)?;
+        }
+        Event::MessageUpdate(updated) => {
+            log::debug!("Received MessageUpdate event to message {}", updated.id);
+            events::message_update(updated, http, pool, config)
+                .await
+                .change_context(EventError::Message)?;
+        }
+        Event::MessageDelete(deleted) => {
+            log::debug!("Received MessageDelete event to message {}", deleted.id);
+            events::message_delete(deleted, http, pool, config)
+                .await
+                .change_context(EventError::Message)?;
+        }
+        Event::MessageDeleteBulk(deleted) => {
+            log::debug!(
+                "Received MessageDeleteBulk event to {} messages",
+                deleted.ids.len()
+            );
+            events::message_delete_bulk(deleted, http, pool, config)
+                .await
+                .change_context(EventError::Message)?;
+        }
+        Event::ChannelDelete(deleted) => {
+            log::debug!("Received ChannelDelete event to channel {}", deleted.id);
+            events::channel_delete(deleted, http, pool, config)
+                .await
+                .change_context(EventError::Channel)?;
+        }
+        Event::InteractionCreate(interaction) => {
+            log::debug!("Received InteractionCreate event {}", interaction.id);
+            events::interaction_create(interaction, http, pool, config)
+                .await
+                .change_context(EventError::Interaction)?;
         }
         Event::GatewayHello(_) => {
             log::debug!("Connected to Discord gateway");
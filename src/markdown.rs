@@ -0,0 +1,452 @@
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+
+/// The marker that opens and closes a block of code.
+const CODE_FENCE: &str = "```";
+
+/// The most characters of the line opening a block of code carried over to the next piece, so that
+/// the room reserved for reopening the block is never exceeded.
+const MAX_FENCE_LENGTH: usize = 20;
+
+/// Room left in each piece for closing a block of code, and reopening it in the next piece.
+const FENCE_RESERVE: usize = MAX_FENCE_LENGTH + CODE_FENCE.len() + 2;
+
+/// The boundaries Markdown is split at, from most to least preferred.
+const SEPARATORS: [&str; 3] = ["\n\n", "\n", " "];
+
+/// Matches an HTML entity, named or numbered.
+static ENTITY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"&(#[0-9]{1,7}|#[xX][0-9a-fA-F]{1,6}|[a-zA-Z]+);").expect("valid entity regex")
+});
+
+/// Matches a user, role or channel mention, or a mention of a command.
+static MENTION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<(@[!&]?|#|/)").expect("valid mention regex"));
+
+/// Matches `@everyone` and `@here`.
+static MASS_MENTION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"@(everyone|here)").expect("valid mass mention regex"));
+
+/// Matches a heading smaller than Discord renders.
+static SMALL_HEADING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s{0,3}#{4,}\s+(.*?)[\s#]*$").expect("valid heading regex"));
+
+/// Matches the row separating the header of a table from its body.
+static TABLE_SEPARATOR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\|?(\s*:?-+:?\s*\|)+\s*(:?-+:?\s*)?$").expect("valid table separator regex")
+});
+
+/// The amount of characters in `text`, as Discord counts them.
+pub fn length(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Splits Markdown into pieces of at most `max_length` characters.
+///
+/// Pieces are split between paragraphs where possible, then between lines, then between words, and
+/// only split within a word as a last resort. A block of code that spans pieces is closed at the end
+/// of one piece and reopened at the start of the next, so that each piece renders on its own.
+pub fn split_markdown(text: &str, max_length: usize) -> Vec<String> {
+    let text = text.trim();
+    if length(text) <= max_length {
+        return vec![text.to_string()];
+    }
+
+    let capacity = max_length.saturating_sub(FENCE_RESERVE).max(1);
+    balance_code_blocks(split_at(text, capacity, &SEPARATORS))
+}
+
+/// Greedily packs the parts of `text` between the first of the `separators` into pieces of at most
+/// `max_length` characters, splitting parts that are too long at the remaining separators.
+fn split_at(text: &str, max_length: usize, separators: &[&str]) -> Vec<String> {
+    let Some((separator, remaining)) = separators.split_first() else {
+        // there is no boundary left to split at, so split between characters
+        let characters = text.chars().collect::<Vec<_>>();
+        return characters
+            .chunks(max_length)
+            .map(|chunk| chunk.iter().collect())
+            .collect();
+    };
+
+    let mut pieces = vec![];
+    let mut current = String::new();
+
+    for part in text.split(separator) {
+        let parts = match length(part) > max_length {
+            true => split_at(part, max_length, remaining),
+            false => vec![part.to_string()],
+        };
+
+        for part in parts {
+            if current.is_empty() {
+                current = part;
+            } else if length(&current) + length(separator) + length(&part) <= max_length {
+                current.push_str(separator);
+                current.push_str(&part);
+            } else {
+                pieces.push(std::mem::replace(&mut current, part));
+            }
+        }
+    }
+    pieces.push(current);
+
+    pieces
+        .into_iter()
+        .filter(|piece| !piece.trim().is_empty())
+        .collect()
+}
+
+/// Closes the blocks of code left open at the end of a piece, and reopens them in the next.
+fn balance_code_blocks(pieces: Vec<String>) -> Vec<String> {
+    let mut open_fence: Option<String> = None;
+
+    pieces
+        .into_iter()
+        .map(|piece| {
+            let mut balanced = match &open_fence {
+                Some(fence) => format!("{fence}\n{piece}"),
+                None => piece.clone(),
+            };
+
+            for line in piece.lines() {
+                let line = line.trim();
+                if line.starts_with(CODE_FENCE) {
+                    open_fence = match open_fence {
+                        Some(_) => None,
+                        None => Some(line.chars().take(MAX_FENCE_LENGTH).collect()),
+                    };
+                }
+            }
+
+            if open_fence.is_some() {
+                balanced.push('\n');
+                balanced.push_str(CODE_FENCE);
+            }
+
+            balanced
+        })
+        .collect()
+}
+
+/// Shortens Markdown to at most `max_length` characters, cutting it at the best boundary.
+pub fn truncate_markdown(text: &str, max_length: usize) -> String {
+    let piece = split_markdown(text, max_length)
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+
+    // lengths too short to close a block of code in are cut between characters
+    match length(&piece) > max_length {
+        true => piece.chars().take(max_length).collect(),
+        false => piece,
+    }
+}
+
+/// Joins `lines` into text of at most `max_length` characters, leaving out the lines that do not
+/// fit.
+pub fn join_lines(lines: &[String], max_length: usize) -> String {
+    let mut joined = String::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let remaining = lines.len() - i;
+        let more = format!("…and {remaining} more");
+        // always leave room to mention how many lines were left out
+        let reserved = if remaining > 1 { length(&more) + 1 } else { 0 };
+
+        if length(&joined) + length(line) + 1 + reserved > max_length {
+            joined.push_str(&more);
+            break;
+        }
+
+        joined.push_str(line);
+        joined.push('\n');
+    }
+
+    joined.trim_end().to_string()
+}
+
+/// Rewrites Markdown converted from a feed into the Markdown Discord renders, and makes sure it
+/// cannot mention anyone.
+///
+/// HTML entities are decoded, headings smaller than Discord renders become bold text, and tables
+/// are shown as blocks of code. Blocks of code are left as they are.
+pub fn sanitize_markdown(markdown: &str) -> String {
+    let mut lines = Vec::new();
+    let mut table = Vec::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        if line.trim_start().starts_with(CODE_FENCE) {
+            in_code_block = !in_code_block;
+            lines.push(line.to_string());
+            continue;
+        }
+        if in_code_block {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let line = decode_entities(line);
+
+        // the rows of a table are gathered until it ends
+        if line.trim_start().starts_with('|') {
+            if !TABLE_SEPARATOR.is_match(line.trim()) {
+                table.push(line.trim().to_string());
+            }
+            continue;
+        }
+        if !table.is_empty() {
+            lines.push(table_block(&mut table));
+        }
+
+        let line = match SMALL_HEADING.captures(&line) {
+            Some(heading) if !heading[1].is_empty() => format!("**{}**", &heading[1]),
+            _ => line,
+        };
+        lines.push(escape_mentions(&line));
+    }
+
+    if !table.is_empty() {
+        lines.push(table_block(&mut table));
+    }
+
+    lines.join("\n")
+}
+
+/// Keeps mentions in text from pinging or linking to anyone, leaving inline code untouched.
+///
+/// A backtick without a closing one does not start inline code, so the text after it is escaped.
+pub fn escape_mentions(text: &str) -> String {
+    let parts = text.split('`').collect::<Vec<_>>();
+    let last = parts.len() - 1;
+
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| match i % 2 == 1 && i < last {
+            // parts within inline code, which have a closing backtick
+            true => part.to_string(),
+            false => {
+                let part = MENTION.replace_all(part, r"\<$1");
+                MASS_MENTION.replace_all(&part, "@\u{200B}$1").into_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("`")
+}
+
+/// Decodes the HTML entities in `text`, leaving unknown entities as they are.
+fn decode_entities(text: &str) -> String {
+    ENTITY
+        .replace_all(text, |entity: &Captures| {
+            let name = &entity[1];
+            let decoded = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => name
+                    .strip_prefix("#x")
+                    .or_else(|| name.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| name.strip_prefix('#').map(str::parse))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            };
+
+            decoded
+                .map(String::from)
+                .unwrap_or_else(|| entity[0].to_string())
+        })
+        .into_owned()
+}
+
+/// Shows the rows of a table as a block of code, as Discord does not render tables.
+fn table_block(table: &mut Vec<String>) -> String {
+    let rows = std::mem::take(table).join("\n");
+    format!("{CODE_FENCE}\n{rows}\n{CODE_FENCE}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_one_piece() {
+        assert_eq!(split_markdown("  hello world  ", 100), vec!["hello world"]);
+    }
+
+    #[test]
+    fn splits_between_paragraphs_first() {
+        let first = "a".repeat(60);
+        let second = "b".repeat(60);
+        let text = format!("{first}\n\n{second}");
+
+        assert_eq!(split_markdown(&text, 100), vec![first, second]);
+    }
+
+    #[test]
+    fn splits_between_words_then_characters() {
+        let text = format!("{} {}", "word ".repeat(30).trim(), "x".repeat(200));
+        let pieces = split_markdown(&text, 50);
+
+        assert!(pieces.iter().all(|piece| length(piece) <= 50));
+        assert!(pieces[0].starts_with("word word"));
+        assert_eq!(pieces.concat().replace(' ', ""), text.replace(' ', ""));
+    }
+
+    #[test]
+    fn pieces_never_exceed_the_maximum_length() {
+        let text =
+            "```rust\n".to_string() + &"let x = 1;\n".repeat(500) + "```\n" + &"é ".repeat(2000);
+
+        for max_length in [40, 100, 1024, 4096] {
+            for piece in split_markdown(&text, max_length) {
+                assert!(length(&piece) <= max_length, "{max_length}: {piece:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn code_blocks_are_reopened_across_pieces() {
+        let text = "```rust\n".to_string() + &"let x = 1;\n".repeat(50) + "```";
+        let pieces = split_markdown(&text, 200);
+
+        assert!(pieces.len() > 1);
+        for piece in &pieces {
+            assert_eq!(piece.matches(CODE_FENCE).count() % 2, 0, "{piece:?}");
+        }
+        assert!(pieces[1].starts_with("```rust\n"));
+    }
+
+    #[test]
+    fn balance_code_blocks_closes_open_blocks() {
+        let pieces = vec![
+            "```py\nprint(1)".to_string(),
+            "print(2)\n```".to_string(),
+            "done".to_string(),
+        ];
+
+        assert_eq!(
+            balance_code_blocks(pieces),
+            vec!["```py\nprint(1)\n```", "```py\nprint(2)\n```", "done"]
+        );
+    }
+
+    #[test]
+    fn truncate_keeps_short_text() {
+        assert_eq!(truncate_markdown("short", 256), "short");
+    }
+
+    #[test]
+    fn truncate_cuts_at_a_word() {
+        let truncated = truncate_markdown(&"word ".repeat(100), 256);
+
+        assert!(length(&truncated) <= 256);
+        assert!(truncated.ends_with("word"));
+    }
+
+    #[test]
+    fn truncate_respects_tiny_lengths() {
+        let text = "```\ncode that is quite long\n```";
+
+        assert_eq!(truncate_markdown(text, 0), "");
+        for max_length in 1..=FENCE_RESERVE + 1 {
+            assert!(length(&truncate_markdown(text, max_length)) <= max_length);
+        }
+    }
+
+    #[test]
+    fn truncate_counts_characters_rather_than_bytes() {
+        let truncated = truncate_markdown(&"ü".repeat(300), 256);
+
+        // room is always left for closing a block of code
+        assert_eq!(length(&truncated), 256 - FENCE_RESERVE);
+    }
+
+    #[test]
+    fn join_lines_mentions_the_lines_left_out() {
+        let lines = (0..100).map(|i| format!("line {i}")).collect::<Vec<_>>();
+        let joined = join_lines(&lines, 50);
+
+        assert!(length(&joined) <= 50);
+        assert!(joined.starts_with("line 0\nline 1"));
+        assert!(joined.ends_with("more"));
+    }
+
+    #[test]
+    fn mentions_are_escaped() {
+        assert_eq!(
+            escape_mentions("hi <@123> <@!456> <@&789> <#10> </feed:11>"),
+            r"hi \<@123> \<@!456> \<@&789> \<#10> \</feed:11>"
+        );
+        assert_eq!(
+            escape_mentions("@everyone and @here"),
+            "@\u{200B}everyone and @\u{200B}here"
+        );
+    }
+
+    #[test]
+    fn code_spans_are_left_untouched() {
+        assert_eq!(
+            escape_mentions("ping `<@&123> @everyone` now <@&123>"),
+            r"ping `<@&123> @everyone` now \<@&123>"
+        );
+    }
+
+    #[test]
+    fn unmatched_backticks_do_not_stop_escaping() {
+        assert_eq!(
+            escape_mentions("Don`t forget <@&123> @everyone"),
+            "Don`t forget \\<@&123> @\u{200B}everyone"
+        );
+        assert_eq!(escape_mentions("`a` b`c <@1>"), r"`a` b`c \<@1>");
+    }
+
+    #[test]
+    fn entities_are_decoded() {
+        assert_eq!(
+            decode_entities("&amp; &lt;b&gt; &quot;hi&quot; &apos;&nbsp;"),
+            "& <b> \"hi\" ' "
+        );
+        assert_eq!(decode_entities("&#233; &#x1F600; &#X41;"), "é 😀 A");
+        assert_eq!(
+            decode_entities("&unknown; &#xZZ; &#1114112;"),
+            "&unknown; &#xZZ; &#1114112;"
+        );
+    }
+
+    #[test]
+    fn decoded_entities_cannot_mention() {
+        assert_eq!(
+            sanitize_markdown("&lt;@&amp;123&gt; &#64;everyone"),
+            "\\<@&123> @\u{200B}everyone"
+        );
+    }
+
+    #[test]
+    fn small_headings_become_bold() {
+        assert_eq!(
+            sanitize_markdown("# Title\n#### Small ####\n##### Smaller"),
+            "# Title\n**Small**\n**Smaller**"
+        );
+    }
+
+    #[test]
+    fn tables_become_code_blocks() {
+        assert_eq!(
+            sanitize_markdown("Before\n| a | b |\n|---|---|\n| 1 | 2 |\nAfter"),
+            "Before\n```\n| a | b |\n| 1 | 2 |\n```\nAfter"
+        );
+    }
+
+    #[test]
+    fn code_blocks_are_not_sanitized() {
+        let code = "```\n&amp; <@&123>\n#### not a heading\n```";
+
+        assert_eq!(sanitize_markdown(code), code);
+    }
+}
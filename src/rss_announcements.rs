@@ -1,38 +1,154 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use chrono::{TimeZone, Utc};
+use chrono::Utc;
+use chrono_tz::Tz;
 use error_stack::{Report, ResultExt};
-use feed_rs::model::Feed;
-use log::debug;
-use sqlx::SqlitePool;
-use twilight_http::Client;
+use feed_rs::model::{Entry, Feed};
+use rand::Rng;
+use reqwest::{
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
+use sha2::{Digest, Sha256};
+use sqlx::{Connection, SqlitePool};
+use tokio::{
+    sync::{Notify, Semaphore},
+    task::{AbortHandle, JoinSet},
+};
 use twilight_model::{
-    channel::message::{embed::EmbedAuthor, Embed},
+    channel::message::{
+        embed::{EmbedAuthor, EmbedField, EmbedFooter, EmbedImage},
+        Embed,
+    },
     id::{
-        marker::{ChannelMarker, RoleMarker},
+        marker::{ChannelMarker, MessageMarker, RoleMarker},
         Id,
     },
     util::Timestamp,
 };
 
-use crate::error::RssError;
+use crate::{
+    announcement_digest::{enqueue_digest_item, replace_digest_item, wake_digests, DigestItem},
+    announcement_feeds::list_feeds,
+    announcement_filters::feed_filter,
+    announcement_media::{extract_media, strip_images, AnnouncementMedia},
+    announcement_outbox::{
+        enqueue_announcement, replace_pending_announcement, wake_outbox, Delivery, OutboxFile,
+        OutboxMessage, OutboxThread, OutboxWebhook,
+    },
+    announcement_pings::feed_pings,
+    config::AnnouncementFeed,
+    error::RssError,
+    markdown::{
+        escape_mentions, join_lines, length, sanitize_markdown, split_markdown, truncate_markdown,
+    },
+};
+
+/// The most embeds Discord allows on a message.
+const MAX_EMBEDS: usize = 10;
+
+/// The most images Discord shows in a gallery of embeds.
+const MAX_GALLERY_IMAGES: usize = 4;
+
+/// The most characters Discord allows in the value of an embed field.
+const MAX_FIELD_LENGTH: usize = 1024;
+
+/// The most characters Discord allows across all embeds of a message.
+const MAX_EMBED_TOTAL_LENGTH: usize = 6000;
+
+/// The most characters Discord allows in the title of an embed, and in the name of its author.
+const MAX_TITLE_LENGTH: usize = 256;
+
+/// The most characters Discord allows in the description of an embed.
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+
+/// Appended to announcements that were too long to post in full.
+const ATTACHED_NOTICE: &str = "\n\n*The full announcement is attached.*";
+
+/// The longest a failing feed waits before it is checked again.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// The HTTP cache validators a feed was last fetched with.
+///
+/// Sending them back lets the server reply that the feed has not changed, rather than sending all
+/// of it again.
+#[derive(Debug, Default)]
+pub struct CacheValidators {
+    /// The `ETag` header of the last response.
+    pub etag: Option<String>,
+    /// The `Last-Modified` header of the last response.
+    pub last_modified: Option<String>,
+}
+
+/// The response to fetching a feed.
+pub enum FeedResponse {
+    /// The feed changed since it was last fetched.
+    Modified {
+        feed: Box<Feed>,
+        validators: CacheValidators,
+    },
+    /// The feed is the same as when it was last fetched.
+    NotModified,
+}
+
+/// How the feeds fetched since the bot started responded, logged periodically.
+#[derive(Debug, Default)]
+struct FetchCounters {
+    /// Feeds that were downloaded and parsed.
+    modified: AtomicU64,
+    /// Feeds the server replied had not changed.
+    not_modified: AtomicU64,
+    /// Feeds that could not be fetched or parsed.
+    failed: AtomicU64,
+}
 
 /// Retrieves the announcements for a specific channel at a `url` specified.
+///
+/// The `validators` of the last fetch are sent along, so that nothing is downloaded if the feed
+/// has not changed since.
 pub async fn get_channel_announcements(
     web_client: &reqwest::Client,
     url: &String,
-) -> Result<Feed, Report<RssError>> {
+    validators: &CacheValidators,
+) -> Result<FeedResponse, Report<RssError>> {
     log::debug!("Fetching announcements at {url}");
-    let rss_feed = web_client
-        .get(url)
-        .send()
-        .await
-        .change_context(RssError::Fetch)?
+    let mut request = web_client.get(url);
+    if let Some(etag) = &validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let response = request.send().await.change_context(RssError::Fetch)?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        log::debug!("RSS feed at {url} was not modified");
+        return Ok(FeedResponse::NotModified);
+    }
+
+    let response = response
         .error_for_status()
-        .change_context(RssError::Fetch)?
-        .bytes()
-        .await
         .change_context(RssError::Fetch)?;
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+    };
+    let validators = CacheValidators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+
+    let rss_feed = response.bytes().await.change_context(RssError::Fetch)?;
     log::debug!("Received RSS feed response, attempting to parse...");
 
     let rss_feed = feed_rs::parser::Builder::new()
@@ -42,235 +158,930 @@ pub async fn get_channel_announcements(
         .change_context(RssError::Read)?;
     log::debug!("Parsed RSS response to Feed");
 
-    Ok(rss_feed)
+    Ok(FeedResponse::Modified {
+        feed: Box::new(rss_feed),
+        validators,
+    })
 }
 
-/// Handles the announcement feed given a list of announcement URLs.
+/// Fingerprints the title and body of an entry, so that edits to it can be noticed.
+fn entry_hash(entry: &Entry) -> String {
+    let mut hasher = Sha256::new();
+    if let Some(title) = &entry.title {
+        hasher.update(title.content.as_bytes());
+    }
+    hasher.update([0]);
+    if let Some(body) = entry_body(entry) {
+        hasher.update(body.as_bytes());
+    }
+
+    format!("{:x}", hasher.finalize())
+}
+
+/// The body of an entry, which may either be text or html.
+pub fn entry_body(entry: &Entry) -> Option<&str> {
+    entry
+        .content
+        .as_ref()
+        .and_then(|content| content.body.as_deref())
+        // if there was no main `entry.content`, we look to `entry.summary` instead
+        .or(entry
+            .summary
+            .as_ref()
+            .map(|summary| summary.content.as_str()))
+}
+
+/// The amount of characters of an embed that count towards the total Discord allows on a message.
+fn embed_length(embed: &Embed) -> usize {
+    let text = |text: Option<&str>| text.map(length).unwrap_or_default();
+
+    text(embed.title.as_deref())
+        + text(embed.description.as_deref())
+        + text(embed.author.as_ref().map(|author| author.name.as_str()))
+        + text(embed.footer.as_ref().map(|footer| footer.text.as_str()))
+        + embed
+            .fields
+            .iter()
+            .map(|field| length(&field.name) + length(&field.value))
+            .sum::<usize>()
+}
+
+/// The course a feed belongs to, taken from its title.
+pub fn course_name(feed: &Feed) -> Option<String> {
+    // a feed title will typically be like
+    // CLASS_NAME CLASS_NUMBER: Long Class Description announcements feed
+    feed.title.as_ref().map(|title| {
+        title
+            .content
+            .split(':')
+            .next()
+            .unwrap_or("Unknown class")
+            .to_owned()
+    })
+}
+
+/// An embed image showing the picture at `url`.
+fn image(url: &str) -> EmbedImage {
+    EmbedImage {
+        height: None,
+        proxy_url: None,
+        url: url.to_string(),
+        width: None,
+    }
+}
+
+/// The field listing the files attached to an announcement, if it has any.
+fn attachments_field(media: &AnnouncementMedia) -> Option<EmbedField> {
+    if media.files.is_empty() {
+        return None;
+    }
+
+    let lines = media
+        .files
+        .iter()
+        .map(|(name, url)| format!("[{}]({url})", name.replace(['[', ']'], "")))
+        .collect::<Vec<_>>();
+
+    Some(EmbedField {
+        inline: false,
+        name: "Attachments".to_string(),
+        value: join_lines(&lines, MAX_FIELD_LENGTH),
+    })
+}
+
+/// Creates the message an entry of the feed is announced with, marking it if the entry has been
+/// `edited` since it was first announced. The message pings the `roles`, and nobody else.
 ///
-/// Checks for new announcements every `check_interval` and posts them to the
-/// specified channel ID.
-pub async fn handle_announcements(
-    announcement_urls: Vec<(String, Id<ChannelMarker>, Option<Id<RoleMarker>>)>,
-    pool: SqlitePool,
-    client: Arc<Client>,
-    check_interval: Duration,
+/// Long entries are spread across several embeds. Entries too long to fit on a message at all are
+/// shortened, with the full text attached as a Markdown file. Images of the entry are shown as a
+/// gallery, and the files it links to are listed in a field.
+pub fn announcement_message(
+    roles: Vec<Id<RoleMarker>>,
+    feed: &Feed,
+    entry: &Entry,
+    edited: bool,
+) -> Result<OutboxMessage, Report<RssError>> {
+    let html = entry_body(entry).unwrap_or_default();
+    let media = extract_media(entry, html);
+    let url = entry.links.first().map(|link| link.href.clone());
+
+    // Discord shows embeds that share a URL as a single gallery, so without a URL only one image
+    // can be shown
+    let shown_images = match url {
+        Some(_) => MAX_GALLERY_IMAGES,
+        None => 1,
+    };
+    let images = &media.images[..media.images.len().min(shown_images)];
+
+    // `body` may either be text or html
+    // if it is html, we need to parse it to discord markdown
+    // the images that are shown in embeds of their own are left out, the rest stay as links
+    let body = sanitize_markdown(&strip_images(&html2md::parse_html(html), images));
+
+    let mut embed = Embed {
+        author: Some(EmbedAuthor {
+            name: truncate_markdown(
+                &format!(
+                    "{} ({})",
+                    entry
+                        .authors
+                        .iter()
+                        .map(|author| author.name.as_str())
+                        .collect::<Vec<&str>>()
+                        .join(", "),
+                    course_name(feed).unwrap_or(entry.id.clone())
+                ),
+                MAX_TITLE_LENGTH,
+            ),
+            icon_url: None,
+            proxy_icon_url: None,
+            url: None,
+        }),
+        color: Some(15844367),
+        description: None,
+        title: entry
+            .title
+            .as_ref()
+            .map(|title| truncate_markdown(&escape_mentions(&title.content), MAX_TITLE_LENGTH)),
+        url,
+        fields: attachments_field(&media).into_iter().collect(),
+        footer: edited.then(|| EmbedFooter {
+            icon_url: None,
+            proxy_icon_url: None,
+            text: "Edited".to_string(),
+        }),
+        timestamp: entry
+            .published
+            .or(entry.updated)
+            .map(|date| Timestamp::from_micros(date.timestamp_micros()))
+            .transpose()
+            .change_context(RssError::Post)?,
+        image: images.first().map(|url| image(url)),
+        kind: "rich".to_string(),
+        provider: None,
+        thumbnail: None,
+        video: None,
+    };
+
+    let gallery = match &embed.url {
+        Some(url) => images
+            .iter()
+            .skip(1)
+            .map(|image_url| Embed {
+                author: None,
+                color: Some(15844367),
+                description: None,
+                title: None,
+                url: Some(url.clone()),
+                fields: vec![],
+                footer: None,
+                timestamp: None,
+                image: Some(image(image_url)),
+                kind: "rich".to_string(),
+                provider: None,
+                thumbnail: None,
+                video: None,
+            })
+            .collect(),
+        None => vec![],
+    };
+
+    let content = roles
+        .iter()
+        .map(|role_id| format!("<@&{role_id}>"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if body.trim().is_empty() {
+        return Ok(OutboxMessage {
+            content,
+            embeds: [embed].into_iter().chain(gallery).collect(),
+            files: vec![],
+            roles,
+            thread: None,
+            webhook: None,
+        });
+    }
+
+    let available = MAX_EMBED_TOTAL_LENGTH.saturating_sub(embed_length(&embed));
+    let pieces = split_markdown(&body, MAX_DESCRIPTION_LENGTH);
+    let fits = pieces.len() + gallery.len() <= MAX_EMBEDS
+        && pieces.iter().map(|piece| length(piece)).sum::<usize>() <= available;
+
+    if !fits {
+        // the full text is attached either way, so the start of it is only shown if there is room
+        let max_length = available
+            .min(MAX_DESCRIPTION_LENGTH)
+            .saturating_sub(length(ATTACHED_NOTICE));
+        embed.description = match max_length {
+            0 => None,
+            _ => Some(truncate_markdown(&body, max_length) + ATTACHED_NOTICE),
+        };
+
+        return Ok(OutboxMessage {
+            content,
+            embeds: [embed].into_iter().chain(gallery).collect(),
+            files: vec![OutboxFile {
+                filename: "announcement.md".to_string(),
+                content: body,
+            }],
+            roles,
+            thread: None,
+            webhook: None,
+        });
+    }
+
+    let mut pieces = pieces.into_iter();
+    embed.description = pieces.next();
+
+    // the gallery has to follow the embed it shares a URL with
+    let mut embeds = vec![embed];
+    embeds.extend(gallery);
+    // continuations have no URL, as Discord merges embeds that share one
+    embeds.extend(pieces.map(|piece| Embed {
+        author: None,
+        color: Some(15844367),
+        description: Some(piece),
+        title: None,
+        url: None,
+        fields: vec![],
+        footer: None,
+        timestamp: None,
+        image: None,
+        kind: "rich".to_string(),
+        provider: None,
+        thumbnail: None,
+        video: None,
+    }));
+
+    // the edited marker belongs at the very end of the announcement
+    if edited && embeds.len() > 1 {
+        let footer = embeds[0].footer.take();
+        if let Some(last) = embeds.last_mut() {
+            last.footer = footer;
+        }
+    }
+
+    Ok(OutboxMessage {
+        content,
+        embeds,
+        files: vec![],
+        roles,
+        thread: None,
+        webhook: None,
+    })
+}
+
+/// The discussion thread to open on the announcement of an entry, if the feed opens threads.
+fn announcement_thread(announcement: &AnnouncementFeed, entry: &Entry) -> Option<OutboxThread> {
+    announcement
+        .thread_archive_duration
+        .map(|duration| OutboxThread {
+            name: entry
+                .title
+                .as_ref()
+                .map(|title| title.content.clone())
+                .unwrap_or_default(),
+            auto_archive_duration: duration.number(),
+        })
+}
+
+/// How the announcements of a feed appear, if the feed is posted through a webhook.
+fn announcement_webhook(announcement: &AnnouncementFeed, feed: &Feed) -> Option<OutboxWebhook> {
+    announcement.webhook.as_ref().map(|webhook| OutboxWebhook {
+        username: webhook
+            .name
+            .clone()
+            .or_else(|| announcement.label.clone())
+            .or_else(|| course_name(feed))
+            .unwrap_or_else(|| "Announcements".to_string()),
+        avatar_url: webhook.avatar_url.clone(),
+    })
+}
+
+/// Checks a feed for new or edited entries once, queuing their announcements to be delivered to its
+/// channel.
+///
+/// The first time a feed is read, its existing entries are only recorded, so that the channel is
+/// not flooded with old announcements. New entries kept out by the filter rules of the feed are
+/// recorded without being announced. Announcements ping the roles of the ping rules they match, or
+/// the role of the feed if no ping rules apply to it.
+///
+/// New entries of a feed with a digest are gathered into the next digest of its channel, due in the
+/// `digest_timezone`, unless they match a ping rule, in which case they are announced straight away.
+async fn check_feed(
+    announcement: &AnnouncementFeed,
+    web_client: &reqwest::Client,
+    pool: &SqlitePool,
+    counters: &FetchCounters,
+    digest_timezone: Tz,
 ) -> Result<(), Report<RssError>> {
-    let web_client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(30))
-        .user_agent(concat!(
-            env!("CARGO_PKG_NAME"),
-            "/",
-            env!("CARGO_PKG_VERSION")
-        ))
-        .build()
-        .expect("Failed to create web client");
+    let url = &announcement.url;
+    // the rules are read on every check, so that changes to them apply straight away
+    let filter = feed_filter(url, pool).await?;
+    let pings = feed_pings(url, pool).await?;
+    let mut pool = pool.acquire().await.change_context(RssError::Database)?;
 
-    loop {
-        log::debug!("Checking for new announcements");
-
-        // check for new announcements
-        for (url, channel, role_id) in announcement_urls.iter() {
-            let feed = get_channel_announcements(&web_client, url).await;
-
-            // if it was an fetch/read error, output error and move to the next feed
-            if let Err(report) = &feed {
-                if matches!(report.current_context(), RssError::Fetch)
-                    || matches!(report.current_context(), RssError::Read)
-                {
-                    log::error!("Failed to fetch feed at {url}: {report:?}, ignoring error and continuing to next announcement stream");
-                    continue;
-                }
-            }
+    let validators = sqlx::query_as!(
+        CacheValidators,
+        r#"
+				SELECT etag, last_modified FROM announcement_fetch WHERE url = ?
+				"#,
+        url
+    )
+    .fetch_optional(&mut *pool)
+    .await
+    .change_context(RssError::Database)?
+    .unwrap_or_default();
 
-            // otherwise, try decode the value and handle logic
-            let feed = feed?;
+    let response = get_channel_announcements(web_client, url, &validators).await;
 
-            // check updated time against database
-            let updated_time = feed
-                .updated
-                .or_else(|| {
-                    // try read the first entry
-                    // and read the `updated` time from there
-					debug!("feed at url {url} did not have a direct `updated` time. using first entry `updated` time");
-                    return feed.entries.first().map(|e| e.published).flatten();
-                })
-                .ok_or(RssError::Read)
-                .attach("Failed to read `updated` field of returned RSS stream")?;
+    if response.is_err() {
+        counters.failed.fetch_add(1, Ordering::Relaxed);
+    }
 
-            let mut pool = pool.acquire().await.change_context(RssError::Database)?;
+    let (feed, validators) = match response? {
+        FeedResponse::Modified { feed, validators } => (feed, validators),
+        FeedResponse::NotModified => {
+            counters.not_modified.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+    };
+    counters.modified.fetch_add(1, Ordering::Relaxed);
 
-            let database_updated_time = sqlx::query!(
-                r#"
-				SELECT last_updated_time FROM announcement_feed WHERE id = ?
+    let feed_title = feed
+        .title
+        .as_ref()
+        .map(|title| title.content.clone())
+        .unwrap_or_else(|| url.to_owned());
+
+    let last_read = sqlx::query!(
+        r#"
+				SELECT last_updated_time, legacy_cutoff FROM announcement_feed WHERE id = ?
 				"#,
-                feed.id
-            )
-            .fetch_optional(&mut *pool)
-            .await
-            .change_context(RssError::Database)?
-            .map(|timestamp| {
-                Utc.timestamp_millis_opt(timestamp.last_updated_time)
-                    .single()
-                    .ok_or(RssError::Database)
-            })
-            .transpose()?;
+        feed.id
+    )
+    .fetch_optional(&mut *pool)
+    .await
+    .change_context(RssError::Database)?;
+    let first_read = last_read.is_none();
+
+    // feeds read before their entries were recorded individually announced the entries updated
+    // since they were last read, so carry on from there until one check has recorded them all
+    let legacy_cutoff = last_read.and_then(|row| row.legacy_cutoff);
 
-            let current_time = Utc::now().timestamp_millis();
-            let Some(database_updated_time) = database_updated_time else {
-                // this is our first time running this announcement stream
-                // mark the current time and go to the next announcement stream
-                // otherwise we will flood the output with announcements
+    let current_time = Utc::now().timestamp_millis();
 
-                sqlx::query!(
-                    r#"
+    // remember when the feed was last read
+    sqlx::query!(
+        r#"
 				INSERT INTO announcement_feed (id, last_updated_time)
 				VALUES (?, ?)
+				ON CONFLICT (id) DO UPDATE SET last_updated_time = excluded.last_updated_time
 				"#,
-                    feed.id,
-                    current_time
-                )
-                .execute(&mut *pool)
-                .await
-                .change_context(RssError::Database)?;
+        feed.id,
+        current_time
+    )
+    .execute(&mut *pool)
+    .await
+    .change_context(RssError::Database)?;
 
-                log::info!(
-                    "First time reading {} stream, not posting it's contents to avoid spam. New posts will be recorded.",
-                    feed.title
-                        .map(|title| title.content)
-                        .unwrap_or_else(|| url.to_owned())
-                );
+    // feeds list their newest entries first, so go through them oldest first
+    for entry in feed.entries.iter().rev() {
+        let content_hash = entry_hash(entry);
+
+        let seen = sqlx::query!(
+            r#"
+SELECT content_hash, channel_id, message_id, thread_id, webhook_id
+FROM announcement_entry
+WHERE feed_id = ? AND entry_id = ?
+	"#,
+            feed.id,
+            entry.id
+        )
+        .fetch_optional(&mut *pool)
+        .await
+        .change_context(RssError::Database)?;
+
+        // nothing to do for entries that have not changed since they were seen
+        if seen
+            .as_ref()
+            .is_some_and(|seen| seen.content_hash == content_hash)
+        {
+            continue;
+        }
 
-                continue;
-            };
+        // the entry is recorded along with its announcement being queued,
+        // so that neither happens without the other
+        let mut transaction = pool.begin().await.change_context(RssError::Database)?;
+
+        // the entry has already been seen, but has been edited since
+        if let Some(seen) = seen {
+            log::info!("Entry {} in {feed_title} was edited", entry.id);
+
+            let roles = pings.roles(announcement.role_id, entry);
+            let mut message = announcement_message(roles, &feed, entry, true)?;
+            message.thread = announcement_thread(announcement, entry);
+            message.webhook = announcement_webhook(announcement, &feed);
+            let thread_id = seen
+                .thread_id
+                .and_then(|thread_id| u64::try_from(thread_id).ok())
+                .and_then(Id::new_checked);
+            let webhook_id = seen
+                .webhook_id
+                .and_then(|webhook_id| u64::try_from(webhook_id).ok())
+                .and_then(Id::new_checked);
+            let posted =
+                seen.channel_id
+                    .zip(seen.message_id)
+                    .and_then(|(channel_id, message_id)| {
+                        Some((
+                            Id::<ChannelMarker>::new_checked(channel_id.try_into().ok()?)?,
+                            Id::<MessageMarker>::new_checked(message_id.try_into().ok()?)?,
+                        ))
+                    });
+
+            // an announcement that has not been posted yet is posted as the entry is now,
+            // as is an entry still waiting for its digest
+            let item = DigestItem::new(announcement, &feed, entry);
+            let replaced =
+                replace_pending_announcement(&feed.id, &entry.id, &message, &mut transaction)
+                    .await?
+                    || replace_digest_item(&feed.id, &entry.id, &item, &mut transaction).await?;
+            if let (false, Some((channel_id, message_id))) = (replaced, posted) {
+                enqueue_announcement(
+                    &feed.id,
+                    &entry.id,
+                    Delivery::Edit {
+                        channel_id,
+                        message_id,
+                        thread_id,
+                        webhook_id,
+                    },
+                    &message,
+                    &mut transaction,
+                )
+                .await?;
+            }
 
-            // update last update time in database
             sqlx::query!(
                 r#"
-					UPDATE announcement_feed
-					SET last_updated_time = ?
-					WHERE id = ?
-					"#,
-                current_time,
-                feed.id
+UPDATE announcement_entry
+SET content_hash = ?
+WHERE feed_id = ? AND entry_id = ?
+	"#,
+                content_hash,
+                feed.id,
+                entry.id
             )
-            .execute(&mut *pool)
+            .execute(&mut *transaction)
+            .await
+            .change_context(RssError::Database)?;
+        } else {
+            sqlx::query!(
+                r#"
+INSERT INTO announcement_entry (feed_id, entry_id, content_hash, posted_time)
+VALUES (?, ?, ?, ?)
+	"#,
+                feed.id,
+                entry.id,
+                content_hash,
+                current_time
+            )
+            .execute(&mut *transaction)
             .await
             .change_context(RssError::Database)?;
 
-            // if we have already processed the last event
-            if database_updated_time == updated_time {
-                log::debug!(
-                    "Database updated time was the same as RSS feed updated time for {}, moving to next RSS feed",
-                    feed.title
-                        .map(|title| title.content)
-                        .unwrap_or_else(|| url.to_owned())
-                );
-                continue;
-            }
-
-            // there are new events, get them all!
-            let new_entries = feed.entries.into_iter().filter_map(|entry| {
+            let entry_title = entry
+                .title
+                .as_ref()
+                .map(|title| title.content.as_str())
+                .unwrap_or(&entry.id);
+            let filtered = filter.check(entry).err();
+            let announced_before = legacy_cutoff.is_some_and(|cutoff| {
                 entry
                     .updated
-                    .map(|date| {
-                        if date > database_updated_time {
-                            Some((entry, date))
-                        } else {
-                            None
-                        }
-                    })
-                    .unwrap_or_default()
+                    .or(entry.published)
+                    .is_none_or(|date| date.timestamp_millis() <= cutoff)
             });
+            let recorded_only = first_read || announced_before;
 
-            for (entry, post_date) in new_entries {
+            // the first time the feed is read, its existing entries are only recorded,
+            // otherwise we will flood the output with announcements
+            if let (false, Some(filtered)) = (recorded_only, &filtered) {
                 log::info!(
-                    "A new post in {} was made at {post_date}",
-                    entry
-                        .title
-                        .clone()
-                        .map(|title| title.content)
-                        .unwrap_or(entry.id.clone())
+                    "A new post in {feed_title} was filtered out, as it {filtered}: {entry_title}"
+                );
+            } else if let (false, Some(digest), false) =
+                (recorded_only, announcement.digest, pings.matches(entry))
+            {
+                let due_time = digest.next_time(Utc::now(), digest_timezone);
+                log::info!(
+                    "A new post in {feed_title} was made, adding it to the digest due at {due_time}: {entry_title}"
+                );
+
+                let item = DigestItem::new(announcement, &feed, entry);
+                enqueue_digest_item(
+                    &feed.id,
+                    &entry.id,
+                    announcement.channel_id,
+                    due_time,
+                    &item,
+                    &mut transaction,
+                )
+                .await?;
+                wake_digests();
+            } else if !recorded_only {
+                log::info!("A new post in {feed_title} was made: {entry_title}");
+
+                let roles = pings.roles(announcement.role_id, entry);
+                let mut message = announcement_message(roles, &feed, entry, false)?;
+                message.thread = announcement_thread(announcement, entry);
+                message.webhook = announcement_webhook(announcement, &feed);
+                enqueue_announcement(
+                    &feed.id,
+                    &entry.id,
+                    Delivery::Create {
+                        channel_id: announcement.channel_id,
+                    },
+                    &message,
+                    &mut transaction,
+                )
+                .await?;
+            }
+        }
+
+        transaction
+            .commit()
+            .await
+            .change_context(RssError::Database)?;
+        wake_outbox();
+    }
+
+    if legacy_cutoff.is_some() {
+        sqlx::query!(
+            r#"
+				UPDATE announcement_feed SET legacy_cutoff = NULL WHERE id = ?
+				"#,
+            feed.id
+        )
+        .execute(&mut *pool)
+        .await
+        .change_context(RssError::Database)?;
+    }
+
+    if first_read {
+        log::info!(
+            "First time reading {feed_title} stream, not posting it's contents to avoid spam. New posts will be recorded."
+        );
+    }
+
+    // only remember the validators once every entry has been handled, otherwise entries
+    // that failed to post would be skipped by the next fetch
+    sqlx::query!(
+        r#"
+				INSERT INTO announcement_fetch (url, etag, last_modified)
+				VALUES (?, ?, ?)
+				ON CONFLICT (url) DO UPDATE SET etag = excluded.etag, last_modified = excluded.last_modified
+				"#,
+        url,
+        validators.etag,
+        validators.last_modified
+    )
+    .execute(&mut *pool)
+    .await
+    .change_context(RssError::Database)?;
+
+    Ok(())
+}
+
+/// Checks a feed for announcements every time its interval passes, for as long as the bot runs.
+///
+/// Feeds that fail to be fetched or read are retried with an exponentially increasing delay, so
+/// that a feed which is down is not hammered. Other failures, such as failing to access the
+/// database, stop the feed from being checked and are returned.
+async fn poll_feed(
+    announcement: AnnouncementFeed,
+    web_client: reqwest::Client,
+    pool: SqlitePool,
+    fetch_permits: Arc<Semaphore>,
+    counters: Arc<FetchCounters>,
+    default_check_interval: Duration,
+    digest_timezone: Tz,
+) -> Result<(), Report<RssError>> {
+    let url = &announcement.url;
+    let check_interval = announcement
+        .check_interval
+        .unwrap_or(default_check_interval);
+    let mut failures = 0;
+
+    loop {
+        let result = {
+            // only so many feeds are fetched at once, so waiting on slow feeds does not hold up others
+            let _permit = fetch_permits
+                .acquire()
+                .await
+                .expect("fetch permits are never closed");
+
+            check_feed(
+                &announcement,
+                &web_client,
+                &pool,
+                &counters,
+                digest_timezone,
+            )
+            .await
+        };
+
+        let delay = match result {
+            Ok(()) => {
+                if failures > 0 {
+                    log::info!("Feed at {url} recovered after {failures} failed checks");
+                    failures = 0;
+                }
+
+                check_interval
+            }
+            Err(report) if matches!(report.current_context(), RssError::Fetch | RssError::Read) => {
+                failures += 1;
+                let delay = backoff(check_interval, failures);
+                log::error!(
+                    "Failed to fetch feed at {url} ({failures} times in a row): {report:?}, trying again in {} seconds",
+                    delay.as_secs()
                 );
 
-                client
-                    .create_message(channel.to_owned())
-                    .content(&match role_id {
-                        Some(id) => format!("<@&{id}>"),
-                        None => String::new(),
-                    })
-                    .change_context(RssError::Post)?
-                    .embeds(&[Embed {
-                        author: Some(EmbedAuthor {
-                            name: format!(
-                                "{} ({})",
-                                entry
-                                    .authors
-                                    .into_iter()
-                                    .map(|author| author.name)
-                                    .collect::<Vec<String>>()
-                                    .join(", "),
-                                // a feed title will typically be like
-                                // CLASS_NAME CLASS_NUMBER: Long Class Description announcements feed
-                                feed.title
-                                    .clone()
-                                    .map(|title| title
-                                        .content
-                                        .split(':')
-                                        .next()
-                                        .to_owned()
-                                        .unwrap_or("Unknown class")
-                                        .to_owned())
-                                    .unwrap_or(entry.id)
-                            ),
-                            icon_url: None,
-                            proxy_icon_url: None,
-                            url: None,
-                        }),
-                        color: Some(15844367),
-                        description: entry
-                            .content
-                            .and_then(|content| {
-                                content.body.map(|body| {
-                                    // `body` may either be text or html
-                                    // if it is html, we need to parse it to discord markdown
-                                    let mut parsed_body = html2md::parse_html(&body);
-                                    parsed_body.truncate(4096);
-                                    parsed_body
-                                })
-                            })
-                            // if there was no main `entry.content`, we look to `entry.summary` instead
-                            .or(entry.summary.map(|summary| summary.content).map(|body| {
-                                // `body` may either be text or html
-                                // if it is html, we need to parse it to discord markdown
-                                let mut parsed_body = html2md::parse_html(&body);
-                                parsed_body.truncate(4096);
-                                parsed_body
-                            })),
-                        title: entry.title.map(|title| title.content),
-                        // use this instead of first() so we can take ownership of the link
-                        url: entry.links.into_iter().next().map(|link| link.href),
-                        fields: vec![],
-                        footer: None,
-                        timestamp: Some(
-                            Timestamp::from_micros(post_date.timestamp_micros())
-                                .change_context(RssError::Post)?,
-                        ),
-                        image: None,
-                        kind: "rich".to_string(),
-                        provider: None,
-                        thumbnail: None,
-                        video: None,
-                    }])
-                    .change_context(RssError::Post)?
-                    .await
-                    .change_context(RssError::Post)?;
+                delay
+            }
+            Err(report) => return Err(report),
+        };
+
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// How long to wait before checking a feed that has failed `failures` times in a row again.
+///
+/// The delay doubles with each failure up to [`MAX_BACKOFF`], and is jittered so that feeds on the
+/// same server that failed together do not retry together.
+fn backoff(check_interval: Duration, failures: u32) -> Duration {
+    let delay = check_interval
+        .saturating_mul(2u32.saturating_pow(failures))
+        .min(MAX_BACKOFF);
+
+    delay.mul_f64(rand::thread_rng().gen_range(0.75..=1.25))
+}
+
+/// Creates the client feeds are fetched with.
+pub fn web_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent(concat!(
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION")
+        ))
+        .build()
+        .expect("Failed to create web client")
+}
+
+/// Lets the announcement task know that the stored feeds have changed.
+static FEEDS_CHANGED: Notify = Notify::const_new();
+
+/// Makes the announcement task pick up changes to the stored feeds, such as a feed being added or
+/// paused.
+pub fn reload_feeds() {
+    FEEDS_CHANGED.notify_one();
+}
+
+/// Handles the announcement feeds stored in the database.
+///
+/// Each enabled feed is checked for new announcements on its own schedule, which are posted to the
+/// feed's channel. Each entry of a feed is posted once, the first time it is seen, and its
+/// announcement is edited whenever the entry changes afterwards. Feeds are started, stopped and
+/// restarted as they are changed with [`reload_feeds`]. Feeds with a digest have their new entries
+/// gathered into digests, which are due in the `digest_timezone`.
+pub async fn handle_announcements(
+    pool: SqlitePool,
+    default_check_interval: Duration,
+    fetch_concurrency: usize,
+    digest_timezone: Tz,
+) -> Result<(), Report<RssError>> {
+    let web_client = web_client();
+
+    let fetch_permits = Arc::new(Semaphore::new(fetch_concurrency));
+    let counters = Arc::new(FetchCounters::default());
+
+    // the feeds currently being checked, keyed by their URL
+    let mut running: HashMap<String, (AnnouncementFeed, AbortHandle)> = HashMap::new();
+    let mut feeds = JoinSet::new();
+
+    let mut log_ticker = tokio::time::interval(default_check_interval);
+    // load the feeds straight away
+    reload_feeds();
+
+    loop {
+        tokio::select! {
+            Some(finished) = feeds.join_next() => match finished {
+                // feeds that were removed or changed are stopped
+                Err(join_error) if join_error.is_cancelled() => {}
+                // a feed only stops checking once it fails
+                result => result.change_context(RssError::Task)??,
+            },
+            _ = FEEDS_CHANGED.notified() => {
+                let enabled = list_feeds(&pool)
+                    .await?
+                    .into_iter()
+                    .filter(|stored| stored.enabled)
+                    .map(|stored| (stored.feed.url.clone(), stored.feed))
+                    .collect::<HashMap<_, _>>();
+
+                // stop the feeds that were removed, paused or changed
+                running.retain(|url, (feed, abort_handle)| {
+                    let keep = enabled.get(url) == Some(feed);
+                    if !keep {
+                        log::info!("Stopped checking feed at {url}");
+                        abort_handle.abort();
+                    }
+
+                    keep
+                });
+
+                for (url, feed) in enabled {
+                    if running.contains_key(&url) {
+                        continue;
+                    }
+
+                    log::info!("Started checking feed at {url}");
+                    let abort_handle = feeds.spawn(poll_feed(
+                        feed.clone(),
+                        web_client.clone(),
+                        pool.clone(),
+                        fetch_permits.clone(),
+                        counters.clone(),
+                        default_check_interval,
+                        digest_timezone,
+                    ));
+                    running.insert(url, (feed, abort_handle));
+                }
+            },
+            _ = log_ticker.tick() => {
+                let modified = counters.modified.load(Ordering::Relaxed);
+                let not_modified = counters.not_modified.load(Ordering::Relaxed);
+                let failed = counters.failed.load(Ordering::Relaxed);
+
+                log::info!(
+                    "Fetched RSS feeds {} times: {modified} modified, {not_modified} not modified, {failed} failed",
+                    modified + not_modified + failed,
+                );
             }
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses an Atom feed with a single entry, linking to the announcement.
+    fn atom_feed(title: &str, author: &str, body: &str) -> Feed {
+        linked_feed(
+            title,
+            author,
+            body,
+            r#"<link href="https://example.com/announcements/1"/>"#,
+        )
+    }
+
+    /// Parses an Atom feed with a single entry, with the `link` element given.
+    fn linked_feed(title: &str, author: &str, body: &str, link: &str) -> Feed {
+        let atom = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>CS 101: Introduction to Computing announcements</title>
+  <id>tag:example.com,2026:feed</id>
+  <updated>2026-10-17T08:00:00Z</updated>
+  <entry>
+    <title>{title}</title>
+    <id>tag:example.com,2026:entry</id>
+    <updated>2026-10-17T08:00:00Z</updated>
+    <author><name>{author}</name></author>
+    {link}
+    <content type="html">{body}</content>
+  </entry>
+</feed>"#
+        );
+
+        feed_rs::parser::parse(atom.as_bytes()).expect("valid Atom feed")
+    }
+
+    /// Checks that a message stays within the limits Discord puts on embeds.
+    fn assert_within_limits(message: &OutboxMessage) {
+        assert!(message.embeds.len() <= MAX_EMBEDS);
+        assert!(message.embeds.iter().map(embed_length).sum::<usize>() <= MAX_EMBED_TOTAL_LENGTH);
+
+        for embed in &message.embeds {
+            let text = |text: Option<&str>| text.map(length).unwrap_or_default();
+            assert!(text(embed.title.as_deref()) <= MAX_TITLE_LENGTH);
+            assert!(
+                text(embed.author.as_ref().map(|author| author.name.as_str())) <= MAX_TITLE_LENGTH
+            );
+            assert!(text(embed.description.as_deref()) <= MAX_DESCRIPTION_LENGTH);
+        }
+    }
+
+    #[test]
+    fn short_announcements_fit_in_one_embed() {
+        let feed = atom_feed(
+            "Exam moved",
+            "Professor",
+            "&lt;p&gt;See you on Friday.&lt;/p&gt;",
+        );
+        let message = announcement_message(vec![], &feed, &feed.entries[0], false).unwrap();
+
+        assert_within_limits(&message);
+        assert_eq!(message.embeds.len(), 1);
+        assert_eq!(message.embeds[0].title.as_deref(), Some("Exam moved"));
+        assert!(message.files.is_empty());
+    }
+
+    #[test]
+    fn long_titles_and_authors_are_truncated() {
+        let feed = atom_feed(&"title ".repeat(100), &"author ".repeat(100), "body");
+        let message = announcement_message(vec![], &feed, &feed.entries[0], true).unwrap();
+
+        assert_within_limits(&message);
+    }
+
+    #[test]
+    fn long_announcements_are_split_across_embeds() {
+        let body = "&lt;p&gt;A long paragraph of text.&lt;/p&gt;".repeat(200);
+        let feed = atom_feed("Syllabus", "Professor", &body);
+        let message = announcement_message(vec![], &feed, &feed.entries[0], true).unwrap();
+
+        assert_within_limits(&message);
+        assert!(message.embeds.len() > 1);
+        assert!(message.files.is_empty());
+        // the edited marker moves to the last embed
+        assert!(message.embeds.last().unwrap().footer.is_some());
+    }
+
+    #[test]
+    fn announcements_too_long_for_a_message_are_attached() {
+        let body = "&lt;p&gt;A long paragraph of text.&lt;/p&gt;".repeat(2000);
+        let feed = atom_feed(&"title ".repeat(100), &"author ".repeat(100), &body);
+        let message = announcement_message(vec![], &feed, &feed.entries[0], true).unwrap();
+
+        assert_within_limits(&message);
+        assert_eq!(message.files.len(), 1);
+        assert!(message.embeds[0]
+            .description
+            .as_ref()
+            .is_some_and(|description| description.ends_with(ATTACHED_NOTICE)));
+    }
+
+    /// An HTML body with `count` images.
+    fn images_body(count: usize) -> String {
+        (1..=count)
+            .map(|i| format!(r#"&lt;img src="https://example.com/{i}.png" alt="image {i}"&gt;"#))
+            .collect()
+    }
+
+    #[test]
+    fn images_beyond_the_gallery_stay_as_links() {
+        let feed = atom_feed("Photos", "Professor", &images_body(6));
+        let message = announcement_message(vec![], &feed, &feed.entries[0], false).unwrap();
+        let description = message.embeds[0].description.clone().unwrap_or_default();
+
+        assert_within_limits(&message);
+        let shown = message
+            .embeds
+            .iter()
+            .filter_map(|embed| embed.image.as_ref())
+            .map(|image| image.url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(shown.len(), MAX_GALLERY_IMAGES);
+        for i in 1..=MAX_GALLERY_IMAGES {
+            assert!(!description.contains(&format!("{i}.png")), "{description}");
+        }
+        assert!(description.contains("[image 5](https://example.com/5.png)"));
+        assert!(description.contains("[image 6](https://example.com/6.png)"));
+    }
+
+    #[test]
+    fn announcements_without_a_link_keep_their_other_images_as_links() {
+        let feed = linked_feed("Photos", "Professor", &images_body(3), "");
+        let message = announcement_message(vec![], &feed, &feed.entries[0], false).unwrap();
+        let description = message.embeds[0].description.clone().unwrap_or_default();
 
-        log::debug!(
-            "Checked all RSS feeds, waiting {} seconds before trying again",
-            check_interval.as_secs()
+        assert_eq!(message.embeds.len(), 1);
+        assert_eq!(
+            message.embeds[0]
+                .image
+                .as_ref()
+                .map(|image| image.url.as_str()),
+            Some("https://example.com/1.png")
         );
-        tokio::time::sleep(check_interval).await;
+        assert!(!description.contains("1.png"), "{description}");
+        assert!(description.contains("[image 2](https://example.com/2.png)"));
+        assert!(description.contains("[image 3](https://example.com/3.png)"));
     }
 }
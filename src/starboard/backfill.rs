@@ -0,0 +1,230 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+
+use error_stack::{Report, ResultExt};
+use sqlx::SqlitePool;
+use twilight_http::Client;
+use twilight_model::id::{
+    marker::{ChannelMarker, MessageMarker},
+    Id,
+};
+
+use crate::{config::ApplicationConfig, error::BackfillError};
+
+use super::sync_starboard_message;
+
+/// The amount of messages requested from the history of a channel at a time.
+const PAGE_SIZE: u16 = 100;
+
+/// How long to wait between requesting pages of history, so that a backfill does not starve the
+/// rest of the bot of its rate limits.
+const PAGE_DELAY: Duration = Duration::from_secs(1);
+
+/// The channels currently being backfilled.
+static RUNNING_BACKFILLS: LazyLock<Mutex<HashSet<Id<ChannelMarker>>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Starts backfilling the starboard from the history of a channel in the background.
+///
+/// Continues from where the last backfill of the channel stopped, unless `restart` is set, in which
+/// case the whole history is evaluated again. Returns `false` if the channel is already being
+/// backfilled.
+pub async fn start_backfill(
+    channel_id: Id<ChannelMarker>,
+    restart: bool,
+    http: Arc<Client>,
+    pool: SqlitePool,
+    config: Arc<ApplicationConfig>,
+) -> Result<bool, Report<BackfillError>> {
+    if RUNNING_BACKFILLS
+        .lock()
+        .expect("backfill lock was poisoned")
+        .contains(&channel_id)
+    {
+        return Ok(false);
+    }
+
+    let channel_id_str = channel_id.to_string();
+    sqlx::query!(
+        r#"
+INSERT INTO starboard_backfill (channel_id, last_message_id, completed)
+VALUES (?, NULL, FALSE)
+ON CONFLICT (channel_id) DO UPDATE
+SET completed = FALSE, last_message_id = CASE WHEN ? THEN NULL ELSE last_message_id END
+	"#,
+        channel_id_str,
+        restart
+    )
+    .execute(&pool)
+    .await
+    .change_context(BackfillError::Progress)?;
+
+    Ok(spawn_backfill(channel_id, http, pool, config))
+}
+
+/// Resumes every backfill that was interrupted, such as by the bot restarting.
+pub async fn resume_backfills(
+    http: Arc<Client>,
+    pool: SqlitePool,
+    config: Arc<ApplicationConfig>,
+) -> Result<(), Report<BackfillError>> {
+    let channel_ids = sqlx::query!(
+        r#"
+SELECT channel_id
+FROM starboard_backfill
+WHERE NOT completed
+	"#
+    )
+    .fetch_all(&pool)
+    .await
+    .change_context(BackfillError::Progress)?;
+
+    for row in channel_ids {
+        let channel_id = u64::try_from(row.channel_id)
+            .ok()
+            .and_then(Id::new_checked)
+            .ok_or(BackfillError::Progress)
+            .attach("Invalid channel id stored for backfill")?;
+
+        log::info!("Resuming starboard backfill of channel {channel_id}");
+        spawn_backfill(channel_id, http.clone(), pool.clone(), config.clone());
+    }
+
+    Ok(())
+}
+
+/// Spawns a task to backfill the channel, returning `false` if it is already being backfilled.
+fn spawn_backfill(
+    channel_id: Id<ChannelMarker>,
+    http: Arc<Client>,
+    pool: SqlitePool,
+    config: Arc<ApplicationConfig>,
+) -> bool {
+    if !RUNNING_BACKFILLS
+        .lock()
+        .expect("backfill lock was poisoned")
+        .insert(channel_id)
+    {
+        return false;
+    }
+
+    tokio::spawn(async move {
+        match backfill_channel(channel_id, &http, &pool, &config).await {
+            Ok(scanned) => {
+                log::info!("Finished starboard backfill of channel {channel_id}, evaluated {scanned} messages")
+            }
+            Err(report) => {
+                log::error!("Starboard backfill of channel {channel_id} failed: {report:?}")
+            }
+        }
+
+        RUNNING_BACKFILLS
+            .lock()
+            .expect("backfill lock was poisoned")
+            .remove(&channel_id);
+    });
+
+    true
+}
+
+/// Pages through the history of a channel from oldest to newest, posting every message that
+/// qualifies for a starboard but was never posted.
+///
+/// Progress is recorded after every page, so that an interrupted backfill can be resumed. Returns
+/// the amount of messages evaluated.
+async fn backfill_channel(
+    channel_id: Id<ChannelMarker>,
+    http: &Client,
+    pool: &SqlitePool,
+    config: &ApplicationConfig,
+) -> Result<u64, Report<BackfillError>> {
+    let channel_id_str = channel_id.to_string();
+    let mut scanned = 0;
+
+    loop {
+        let last_message_id = sqlx::query!(
+            r#"
+SELECT last_message_id
+FROM starboard_backfill
+WHERE channel_id = ?
+	"#,
+            channel_id_str
+        )
+        .fetch_one(pool)
+        .await
+        .change_context(BackfillError::Progress)?
+        .last_message_id;
+
+        // start from the very beginning of the channel if we have not made any progress yet
+        let after: Id<MessageMarker> = last_message_id
+            .and_then(|id| u64::try_from(id).ok())
+            .and_then(Id::new_checked)
+            .unwrap_or(Id::new(1));
+
+        let mut messages = http
+            .channel_messages(channel_id)
+            .after(after)
+            .limit(PAGE_SIZE)
+            .change_context(BackfillError::RetrieveMessages)?
+            .await
+            .change_context(BackfillError::RetrieveMessages)?
+            .models()
+            .await
+            .change_context(BackfillError::RetrieveMessages)?;
+
+        let Some(newest_message_id) = messages.iter().map(|message| message.id).max() else {
+            sqlx::query!(
+                r#"
+UPDATE starboard_backfill
+SET completed = TRUE
+WHERE channel_id = ?
+	"#,
+                channel_id_str
+            )
+            .execute(pool)
+            .await
+            .change_context(BackfillError::Progress)?;
+
+            return Ok(scanned);
+        };
+
+        // post messages in the order they were sent, without letting one message that fails to
+        // post hold up the rest of the channel
+        messages.sort_by_key(|message| message.id);
+        for message in messages
+            .iter()
+            .filter(|message| !message.reactions.is_empty())
+        {
+            if let Err(report) = sync_starboard_message(message, http, pool, config)
+                .await
+                .change_context(BackfillError::Starboard)
+            {
+                log::error!(
+                    "Failed to backfill message {} of channel {channel_id}, skipping it: {report:?}",
+                    message.id
+                );
+            }
+        }
+        scanned += messages.len() as u64;
+
+        let newest_message_id = newest_message_id.to_string();
+        sqlx::query!(
+            r#"
+UPDATE starboard_backfill
+SET last_message_id = ?
+WHERE channel_id = ?
+	"#,
+            newest_message_id,
+            channel_id_str
+        )
+        .execute(pool)
+        .await
+        .change_context(BackfillError::Progress)?;
+
+        log::debug!("Starboard backfill of channel {channel_id} has evaluated {scanned} messages");
+        tokio::time::sleep(PAGE_DELAY).await;
+    }
+}
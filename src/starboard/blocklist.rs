@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use error_stack::{Report, ResultExt};
+use sqlx::{SqliteConnection, SqlitePool};
+use twilight_http::Client;
+use twilight_model::{
+    channel::Message,
+    id::{
+        marker::{ChannelMarker, MessageMarker},
+        Id,
+    },
+};
+
+use crate::{
+    config::{ApplicationConfig, DeletedMessagePolicy},
+    error::{BlocklistError, ReactionError},
+};
+
+use super::{eligibility::channel_ancestry, lock::lock_message, withdraw::remove_starboard_posts};
+
+/// The kinds of things that can be kept off the starboard.
+#[derive(Debug, Clone, Copy)]
+pub enum BlockKind {
+    /// Messages sent by a user.
+    User,
+    /// Messages sent in a channel, or in any channel of a category.
+    Channel,
+    /// A single message.
+    Message,
+}
+
+impl BlockKind {
+    /// The name the kind is stored as in the database.
+    fn as_str(self) -> &'static str {
+        match self {
+            BlockKind::User => "user",
+            BlockKind::Channel => "channel",
+            BlockKind::Message => "message",
+        }
+    }
+}
+
+/// Keeps the user, channel or message with the `target_id` off the starboard.
+///
+/// Returns `false` if it was already blocked.
+pub async fn block(
+    kind: BlockKind,
+    target_id: u64,
+    pool: &SqlitePool,
+) -> Result<bool, Report<BlocklistError>> {
+    let kind = kind.as_str();
+    let target_id = target_id.to_string();
+
+    let result = sqlx::query!(
+        r#"
+INSERT OR IGNORE INTO starboard_blocklist (kind, target_id)
+VALUES (?, ?)
+	"#,
+        kind,
+        target_id
+    )
+    .execute(pool)
+    .await
+    .change_context(BlocklistError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Allows the user, channel or message with the `target_id` onto the starboard again.
+///
+/// Returns `false` if it was not blocked.
+pub async fn unblock(
+    kind: BlockKind,
+    target_id: u64,
+    pool: &SqlitePool,
+) -> Result<bool, Report<BlocklistError>> {
+    let kind = kind.as_str();
+    let target_id = target_id.to_string();
+
+    let result = sqlx::query!(
+        r#"
+DELETE FROM starboard_blocklist
+WHERE kind = ? AND target_id = ?
+	"#,
+        kind,
+        target_id
+    )
+    .execute(pool)
+    .await
+    .change_context(BlocklistError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Removes every starboard post of a message, and blocks it from being posted again.
+///
+/// Returns whether the message had been posted to any starboard.
+pub async fn veto_message(
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    http: &Client,
+    pool: &SqlitePool,
+    config: &ApplicationConfig,
+) -> Result<bool, Report<BlocklistError>> {
+    let _lock = lock_message(message_id).await;
+
+    block(BlockKind::Message, message_id.get(), pool).await?;
+
+    let mut connection = pool
+        .acquire()
+        .await
+        .change_context(BlocklistError::Database)?;
+
+    let removed = remove_starboard_posts(
+        channel_id,
+        message_id,
+        DeletedMessagePolicy::Delete,
+        http,
+        &mut connection,
+        config,
+    )
+    .await
+    .change_context(BlocklistError::RemovePosts)?;
+
+    log::info!("message {message_id} was vetoed from the starboard");
+
+    Ok(removed)
+}
+
+/// Removes the existing starboard posts of the messages kept off the starboard by blocking the
+/// user, channel or message with the `target_id`.
+///
+/// Posts whose author or channel has not been recorded yet are left in place.
+///
+/// Returns how many messages were withdrawn from the starboard.
+pub async fn withdraw_blocked_posts(
+    kind: BlockKind,
+    target_id: u64,
+    http: &Client,
+    pool: &SqlitePool,
+    config: &ApplicationConfig,
+) -> Result<usize, Report<BlocklistError>> {
+    let target_id_str = target_id.to_string();
+
+    let posts = match kind {
+        BlockKind::User => sqlx::query!(
+            r#"
+SELECT DISTINCT message_id, channel_id AS "channel_id!"
+FROM starboard
+WHERE author_id = ? AND channel_id IS NOT NULL
+	"#,
+            target_id_str
+        )
+        .fetch_all(pool)
+        .await
+        .change_context(BlocklistError::Database)?
+        .into_iter()
+        .map(|row| (row.message_id, row.channel_id))
+        .collect::<Vec<_>>(),
+        BlockKind::Channel => sqlx::query!(
+            r#"
+SELECT DISTINCT message_id, channel_id AS "channel_id!"
+FROM starboard
+WHERE channel_id IS NOT NULL
+	"#
+        )
+        .fetch_all(pool)
+        .await
+        .change_context(BlocklistError::Database)?
+        .into_iter()
+        .map(|row| (row.message_id, row.channel_id))
+        .collect(),
+        BlockKind::Message => sqlx::query!(
+            r#"
+SELECT DISTINCT message_id, channel_id AS "channel_id!"
+FROM starboard
+WHERE message_id = ? AND channel_id IS NOT NULL
+	"#,
+            target_id_str
+        )
+        .fetch_all(pool)
+        .await
+        .change_context(BlocklistError::Database)?
+        .into_iter()
+        .map(|row| (row.message_id, row.channel_id))
+        .collect(),
+    };
+
+    // whether each channel is the blocked channel, or lies within it
+    let mut blocked = HashMap::new();
+    let mut withdrawn = 0;
+
+    for (message_id, channel_id) in posts {
+        let (Some(message_id), Some(channel_id)) = (
+            u64::try_from(message_id).ok().and_then(Id::new_checked),
+            u64::try_from(channel_id).ok().and_then(Id::new_checked),
+        ) else {
+            continue;
+        };
+
+        if let BlockKind::Channel = kind {
+            let within = match blocked.get(&channel_id) {
+                Some(within) => *within,
+                None => {
+                    let within = match channel_ancestry(channel_id, http).await {
+                        Ok(ancestry) => ancestry.iter().any(|id| id.get() == target_id),
+                        Err(report) => {
+                            log::warn!(
+                                "Failed to look up the parents of channel {channel_id}, leaving its starboard posts in place: {report:?}"
+                            );
+                            false
+                        }
+                    };
+                    blocked.insert(channel_id, within);
+                    within
+                }
+            };
+
+            if !within {
+                continue;
+            }
+        }
+
+        let _lock = lock_message(message_id).await;
+
+        let mut connection = pool
+            .acquire()
+            .await
+            .change_context(BlocklistError::Database)?;
+
+        let removed = remove_starboard_posts(
+            channel_id,
+            message_id,
+            DeletedMessagePolicy::Delete,
+            http,
+            &mut connection,
+            config,
+        )
+        .await
+        .change_context(BlocklistError::RemovePosts)?;
+
+        if removed {
+            withdrawn += 1;
+        }
+    }
+
+    log::info!(
+        "withdrew {withdrawn} messages from the starboard after blocking {} {target_id}",
+        kind.as_str()
+    );
+
+    Ok(withdrawn)
+}
+
+/// Retrieves the channels and categories whose messages are blocked from the starboard.
+pub(super) async fn blocked_channels(
+    connection: &mut SqliteConnection,
+) -> Result<Vec<Id<ChannelMarker>>, Report<ReactionError>> {
+    let channels = sqlx::query!(
+        r#"
+SELECT target_id
+FROM starboard_blocklist
+WHERE kind = 'channel'
+	"#
+    )
+    .fetch_all(&mut *connection)
+    .await
+    .change_context(ReactionError::Blocklist)?
+    .into_iter()
+    .filter_map(|row| u64::try_from(row.target_id).ok().and_then(Id::new_checked))
+    .collect();
+
+    Ok(channels)
+}
+
+/// Whether the `message` itself, or its author, is blocked from the starboard.
+pub(super) async fn is_message_blocked(
+    message: &Message,
+    connection: &mut SqliteConnection,
+) -> Result<bool, Report<ReactionError>> {
+    let message_id = message.id.to_string();
+    let author_id = message.author.id.to_string();
+
+    let blocked = sqlx::query!(
+        r#"
+SELECT EXISTS (
+	SELECT 1
+	FROM starboard_blocklist
+	WHERE (kind = 'message' AND target_id = ?) OR (kind = 'user' AND target_id = ?)
+) AS "blocked!: bool"
+	"#,
+        message_id,
+        author_id
+    )
+    .fetch_one(&mut *connection)
+    .await
+    .change_context(ReactionError::Blocklist)?
+    .blocked;
+
+    Ok(blocked)
+}
@@ -0,0 +1,300 @@
+use std::collections::HashSet;
+
+use error_stack::{Report, ResultExt};
+use twilight_http::{request::channel::reaction::RequestReactionType, Client};
+use twilight_model::{
+    channel::{message::ReactionType, Message},
+    id::{
+        marker::{ChannelMarker, UserMarker},
+        Id,
+    },
+    user::User,
+};
+
+use crate::{
+    config::{CountingMode, Starboard},
+    error::ReactionError,
+};
+
+/// The maximum amount of users Discord returns for a single reaction request.
+const REACTION_PAGE_SIZE: u16 = 100;
+
+/// The users that reacted to a message with a single emoji, excluding the message author and bots.
+pub struct EmojiReactors {
+    /// The emoji reacted with.
+    pub emoji: ReactionType,
+    /// The eligible users that reacted with the emoji.
+    pub users: Vec<Id<UserMarker>>,
+}
+
+/// The reactions on a message that count towards making it material for a starboard.
+pub struct ReactionTally {
+    /// The amount of eligible reactions, as counted by the configured [`CountingMode`].
+    pub count: u64,
+    /// The emoji with the most eligible reactions, or `None` if no reactions were eligible.
+    pub emoji: Option<ReactionType>,
+}
+
+/// Whether the `emoji` is the one `wanted`, given as either a unicode emoji, or the name or ID of a
+/// custom emoji.
+pub fn emoji_matches(wanted: &str, emoji: &ReactionType) -> bool {
+    match emoji {
+        ReactionType::Unicode { name } => wanted == name,
+        ReactionType::Custom { id, name, .. } => {
+            wanted == id.to_string() || name.as_deref() == Some(wanted)
+        }
+    }
+}
+
+impl Starboard {
+    /// Whether reactions using the `emoji` are counted towards this board.
+    pub fn counts_emoji(&self, emoji: &ReactionType) -> bool {
+        self.emojis.is_empty()
+            || self
+                .emojis
+                .iter()
+                .any(|wanted| emoji_matches(wanted, emoji))
+    }
+
+    /// Whether this board only accepts messages from some channels.
+    pub fn filters_channels(&self) -> bool {
+        !self.include_channels.is_empty() || !self.exclude_channels.is_empty()
+    }
+
+    /// Whether a message in a channel with the given `ancestry` may be posted to this board.
+    ///
+    /// The `ancestry` is the channel the message was sent in, followed by its parent channels.
+    pub fn accepts_channel(&self, ancestry: &[Id<ChannelMarker>]) -> bool {
+        let included = self.include_channels.is_empty()
+            || ancestry
+                .iter()
+                .any(|channel_id| self.include_channels.contains(channel_id));
+        let excluded = ancestry
+            .iter()
+            .any(|channel_id| self.exclude_channels.contains(channel_id));
+
+        included && !excluded
+    }
+}
+
+/// Retrieves the channel with the given `channel_id`, followed by its parent channels.
+///
+/// A message in a thread will have the thread, the channel the thread is in, and the category
+/// of that channel returned.
+pub async fn channel_ancestry(
+    channel_id: Id<ChannelMarker>,
+    http: &Client,
+) -> Result<Vec<Id<ChannelMarker>>, Report<ReactionError>> {
+    let mut ancestry = vec![channel_id];
+
+    // a thread is at most two levels deep, within a channel within a category
+    while ancestry.len() < 3 {
+        let channel = http
+            .channel(ancestry[ancestry.len() - 1])
+            .await
+            .change_context(ReactionError::RetrieveChannel)?
+            .model()
+            .await
+            .change_context(ReactionError::RetrieveChannel)?;
+
+        match channel.parent_id {
+            Some(parent_id) => ancestry.push(parent_id),
+            None => break,
+        }
+    }
+
+    Ok(ancestry)
+}
+
+/// Retrieves the users eligible to count towards a starboard that reacted to a `message`, for
+/// each emoji the `include_emoji` filter accepts.
+///
+/// Reactions by the message author and by bots are never eligible.
+pub async fn fetch_reactors(
+    message: &Message,
+    http: &Client,
+    include_emoji: impl Fn(&ReactionType) -> bool,
+) -> Result<Vec<EmojiReactors>, Report<ReactionError>> {
+    let mut reactors = Vec::new();
+
+    for reaction in message
+        .reactions
+        .iter()
+        .filter(|reaction| include_emoji(&reaction.emoji))
+    {
+        let users = reaction_users(message, &reaction.emoji, http)
+            .await?
+            .into_iter()
+            .filter(|user| !user.bot && user.id != message.author.id)
+            .map(|user| user.id)
+            .collect();
+
+        reactors.push(EmojiReactors {
+            emoji: reaction.emoji.clone(),
+            users,
+        });
+    }
+
+    Ok(reactors)
+}
+
+/// Counts the `reactors` of a message that make it eligible for the `board`.
+///
+/// A user reacting with several emojis is only counted once when counting unique users.
+pub fn tally_reactions(
+    reactors: &[EmojiReactors],
+    mode: &CountingMode,
+    board: &Starboard,
+) -> ReactionTally {
+    let mut unique_users: HashSet<Id<UserMarker>> = HashSet::new();
+    let mut best: Option<(u64, &ReactionType)> = None;
+
+    for reactors in reactors
+        .iter()
+        .filter(|reactors| board.counts_emoji(&reactors.emoji))
+    {
+        let count = reactors.users.len() as u64;
+        if count > 0 && best.is_none_or(|(best_count, _)| count > best_count) {
+            best = Some((count, &reactors.emoji));
+        }
+
+        unique_users.extend(reactors.users.iter().copied());
+    }
+
+    let count = match mode {
+        CountingMode::UniqueUsers => unique_users.len() as u64,
+        CountingMode::SingleEmoji => best.map(|(count, _)| count).unwrap_or_default(),
+    };
+
+    ReactionTally {
+        count,
+        emoji: best.map(|(_, emoji)| emoji.clone()),
+    }
+}
+
+/// Retrieves every user that reacted to the `message` with the `emoji`.
+async fn reaction_users(
+    message: &Message,
+    emoji: &ReactionType,
+    http: &Client,
+) -> Result<Vec<User>, Report<ReactionError>> {
+    let request_emoji = match emoji {
+        ReactionType::Custom { id, name, .. } => RequestReactionType::Custom {
+            id: *id,
+            name: name.as_deref(),
+        },
+        ReactionType::Unicode { name } => RequestReactionType::Unicode { name },
+    };
+
+    let mut users: Vec<User> = Vec::new();
+    loop {
+        let mut request = http
+            .reactions(message.channel_id, message.id, &request_emoji)
+            .limit(REACTION_PAGE_SIZE)
+            .change_context(ReactionError::ReactionUsers)?;
+        if let Some(last_user) = users.last() {
+            request = request.after(last_user.id);
+        }
+
+        let page = request
+            .await
+            .change_context(ReactionError::ReactionUsers)?
+            .models()
+            .await
+            .change_context(ReactionError::ReactionUsers)?;
+
+        let is_last_page = page.len() < usize::from(REACTION_PAGE_SIZE);
+        users.extend(page);
+
+        if is_last_page {
+            return Ok(users);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(emojis: &[&str]) -> Starboard {
+        Starboard {
+            name: "default".to_string(),
+            channel_id: Id::new(1),
+            reaction_requirement: 3,
+            removal_threshold: None,
+            emojis: emojis.iter().map(|emoji| emoji.to_string()).collect(),
+            include_channels: vec![],
+            exclude_channels: vec![],
+        }
+    }
+
+    fn unicode(name: &str) -> ReactionType {
+        ReactionType::Unicode {
+            name: name.to_string(),
+        }
+    }
+
+    fn reactors(emoji: ReactionType, users: &[u64]) -> EmojiReactors {
+        EmojiReactors {
+            emoji,
+            users: users.iter().map(|&user| Id::new(user)).collect(),
+        }
+    }
+
+    #[test]
+    fn unique_users_are_counted_once() {
+        let reactions = [
+            reactors(unicode("⭐"), &[1, 2, 3]),
+            reactors(unicode("🔥"), &[2, 3, 4]),
+        ];
+        let tally = tally_reactions(&reactions, &CountingMode::UniqueUsers, &board(&[]));
+
+        assert_eq!(tally.count, 4);
+        assert_eq!(tally.emoji, Some(unicode("⭐")));
+    }
+
+    #[test]
+    fn single_emoji_counts_the_most_reacted_emoji() {
+        let reactions = [
+            reactors(unicode("⭐"), &[1, 2]),
+            reactors(unicode("🔥"), &[2, 3, 4]),
+        ];
+        let tally = tally_reactions(&reactions, &CountingMode::SingleEmoji, &board(&[]));
+
+        assert_eq!(tally.count, 3);
+        assert_eq!(tally.emoji, Some(unicode("🔥")));
+    }
+
+    #[test]
+    fn only_the_emojis_of_the_board_are_counted() {
+        let reactions = [
+            reactors(unicode("⭐"), &[1, 2]),
+            reactors(unicode("🔥"), &[3, 4, 5]),
+            reactors(
+                ReactionType::Custom {
+                    animated: false,
+                    id: Id::new(42),
+                    name: Some("upvote".to_string()),
+                },
+                &[6],
+            ),
+        ];
+        let tally = tally_reactions(
+            &reactions,
+            &CountingMode::UniqueUsers,
+            &board(&["⭐", "upvote"]),
+        );
+
+        assert_eq!(tally.count, 3);
+        assert_eq!(tally.emoji, Some(unicode("⭐")));
+    }
+
+    #[test]
+    fn messages_without_eligible_reactions_have_no_emoji() {
+        let reactions = [reactors(unicode("⭐"), &[])];
+        let tally = tally_reactions(&reactions, &CountingMode::SingleEmoji, &board(&[]));
+
+        assert_eq!(tally.count, 0);
+        assert_eq!(tally.emoji, None);
+    }
+}
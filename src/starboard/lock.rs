@@ -0,0 +1,40 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex, Weak},
+};
+
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+use twilight_model::id::{marker::MessageMarker, Id};
+
+/// A lock on the starboard posts of a single message.
+type MessageLock = AsyncMutex<()>;
+
+/// The locks of the messages whose starboard posts are currently being worked on.
+///
+/// Only weak references are kept, so a lock is freed once nothing holds or waits on it.
+static MESSAGE_LOCKS: LazyLock<Mutex<HashMap<Id<MessageMarker>, Weak<MessageLock>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Waits until no other task is working on the starboard posts of a message, then holds them
+/// until the returned guard is dropped.
+///
+/// Without this, two reactions added at the same time could both see that a message has no post
+/// yet, and each create one.
+pub(super) async fn lock_message(message_id: Id<MessageMarker>) -> OwnedMutexGuard<()> {
+    let lock = {
+        let mut locks = MESSAGE_LOCKS.lock().expect("message locks were poisoned");
+        // forget about the locks of messages that are no longer being worked on
+        locks.retain(|_, lock| lock.strong_count() > 0);
+
+        match locks.get(&message_id).and_then(Weak::upgrade) {
+            Some(lock) => lock,
+            None => {
+                let lock = Arc::new(MessageLock::new(()));
+                locks.insert(message_id, Arc::downgrade(&lock));
+                lock
+            }
+        }
+    };
+
+    lock.lock_owned().await
+}
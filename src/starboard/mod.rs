@@ -0,0 +1,12 @@
+mod backfill;
+mod blocklist;
+mod eligibility;
+mod lock;
+mod sync;
+mod withdraw;
+
+pub use backfill::{resume_backfills, start_backfill};
+pub use blocklist::{block, unblock, veto_message, withdraw_blocked_posts, BlockKind};
+pub use eligibility::{emoji_matches, ReactionTally};
+pub use sync::{sync_starboard, sync_starboard_message};
+pub use withdraw::withdraw_starboard;
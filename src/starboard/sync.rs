@@ -0,0 +1,294 @@
+use std::{collections::HashMap, sync::Arc};
+
+use error_stack::{Report, ResultExt};
+use sqlx::{SqliteConnection, SqlitePool};
+use twilight_http::Client;
+use twilight_model::{
+    channel::{message::AllowedMentions, Message},
+    id::{
+        marker::{ChannelMarker, MessageMarker},
+        Id,
+    },
+};
+
+use crate::{
+    config::{ApplicationConfig, Starboard},
+    create_starboard_message::{create_starboard_message, format_emoji},
+    error::ReactionError,
+};
+
+use super::{
+    blocklist::{blocked_channels, is_message_blocked},
+    eligibility::{channel_ancestry, fetch_reactors, tally_reactions},
+    lock::lock_message,
+};
+
+/// Brings the starboard posts for a message up to date with the reactions it currently has.
+///
+/// Each board is considered independently. If the message has no post on a board yet, one is
+/// created once it reaches the board's reaction requirement, but only when `may_create` is set.
+/// An existing post has its reaction count refreshed, or is withdrawn entirely if the message has
+/// dropped below the board's removal threshold.
+pub async fn sync_starboard(
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    http: Arc<Client>,
+    pool: SqlitePool,
+    config: Arc<ApplicationConfig>,
+    may_create: bool,
+) -> Result<(), Report<ReactionError>> {
+    let _lock = lock_message(message_id).await;
+
+    // first check which boards the message has already been starboard'd to
+    let mut pool = pool
+        .acquire()
+        .await
+        .change_context(ReactionError::DatabaseConnect)?;
+
+    let starboard_ids = posted_starboards(message_id, &mut pool).await?;
+
+    // nothing to refresh, and we are not allowed to make a new post
+    if starboard_ids.is_empty() && !may_create {
+        return Ok(());
+    }
+
+    // retrieve the reactions the message has now
+    let message = http
+        .message(channel_id, message_id)
+        .await
+        .change_context(ReactionError::RetrieveMessage)?
+        .model()
+        .await
+        .change_context(ReactionError::RetrieveMessage)?;
+
+    update_starboards(
+        &message,
+        starboard_ids,
+        &http,
+        &mut pool,
+        &config,
+        may_create,
+    )
+    .await
+}
+
+/// Brings the starboard posts for a `message` that has already been retrieved up to date,
+/// creating posts on any boards it now qualifies for.
+pub async fn sync_starboard_message(
+    message: &Message,
+    http: &Client,
+    pool: &SqlitePool,
+    config: &ApplicationConfig,
+) -> Result<(), Report<ReactionError>> {
+    let _lock = lock_message(message.id).await;
+
+    let mut pool = pool
+        .acquire()
+        .await
+        .change_context(ReactionError::DatabaseConnect)?;
+
+    let starboard_ids = posted_starboards(message.id, &mut pool).await?;
+
+    update_starboards(message, starboard_ids, http, &mut pool, config, true).await
+}
+
+/// Retrieves the starboard posts of a message, keyed by the name of the board they are on.
+async fn posted_starboards(
+    message_id: Id<MessageMarker>,
+    connection: &mut SqliteConnection,
+) -> Result<HashMap<String, Id<MessageMarker>>, Report<ReactionError>> {
+    let message_id = message_id.to_string();
+
+    sqlx::query!(
+        r#"
+SELECT board, starboard_id
+FROM starboard
+WHERE message_id = ?
+	"#,
+        message_id
+    )
+    .fetch_all(&mut *connection)
+    .await
+    .change_context(ReactionError::PreviousReactionCount)?
+    .into_iter()
+    .map(|row| -> std::result::Result<_, _> {
+        u64::try_from(row.starboard_id).map(|starboard_id| (row.board, Id::new(starboard_id)))
+    })
+    .collect::<Result<_, _>>()
+    .change_context(ReactionError::PreviousReactionCount)
+}
+
+/// Creates, updates or withdraws the post of the `message` on each board, given the boards it has
+/// already been posted to.
+///
+/// Messages that are blocked from the starboard are left untouched.
+async fn update_starboards(
+    message: &Message,
+    starboard_ids: HashMap<String, Id<MessageMarker>>,
+    http: &Client,
+    pool: &mut SqliteConnection,
+    config: &ApplicationConfig,
+    may_create: bool,
+) -> Result<(), Report<ReactionError>> {
+    let channel_id = message.channel_id;
+    let message_id = message.id;
+    let message_id_str = message_id.to_string();
+
+    let blocked_channels = blocked_channels(pool).await?;
+
+    // only look up the parents of the channel if a board or the blocklist cares about them
+    let ancestry = if !blocked_channels.is_empty()
+        || config.starboards.iter().any(Starboard::filters_channels)
+    {
+        channel_ancestry(channel_id, http).await?
+    } else {
+        vec![channel_id]
+    };
+
+    // moderators may have kept the message off the starboard
+    if ancestry
+        .iter()
+        .any(|channel_id| blocked_channels.contains(channel_id))
+        || is_message_blocked(message, pool).await?
+    {
+        log::debug!("message {message_id} is blocked from the starboard");
+        return Ok(());
+    }
+
+    let reactors = fetch_reactors(message, http, |emoji| {
+        config
+            .starboards
+            .iter()
+            .any(|board| board.counts_emoji(emoji))
+    })
+    .await?;
+
+    let author_id = message.author.id.to_string();
+    let channel_id_str = channel_id.to_string();
+    let message_timestamp = message.timestamp.as_micros() / 1000;
+
+    for board in &config.starboards {
+        let starboard_id = starboard_ids.get(&board.name).copied();
+        if starboard_id.is_none() && !(may_create && board.accepts_channel(&ancestry)) {
+            continue;
+        }
+
+        // count the reactions that make the message eligible for this board
+        let tally = tally_reactions(&reactors, &config.counting_mode, board);
+        log::info!(
+            "message {message_id} has {} eligible reactions for the {} starboard now",
+            tally.count,
+            board.name
+        );
+
+        // details recorded for leaderboards and statistics
+        let emoji = tally.emoji.as_ref().map(format_emoji);
+        let reaction_count = tally.count as i64;
+
+        if let Some(starboard_message_id) = starboard_id {
+            // withdraw the starboard message if it no longer has enough reactions
+            if board
+                .removal_threshold
+                .is_some_and(|threshold| tally.count < threshold.into())
+            {
+                http.delete_message(board.channel_id, starboard_message_id)
+                    .await
+                    .change_context(ReactionError::RemoveStarboardMessage)?;
+
+                sqlx::query!(
+                    r#"
+DELETE FROM starboard
+WHERE message_id = ? AND board = ?
+		"#,
+                    message_id_str,
+                    board.name
+                )
+                .execute(&mut *pool)
+                .await
+                .change_context(ReactionError::RemoveStarboardMessage)?;
+
+                log::info!(
+                    "message {message_id} dropped below the removal threshold, removed it from the {} starboard",
+                    board.name
+                );
+
+                continue;
+            }
+
+            // update the starboard message we already made
+            // to display the new amount of reactions
+            let new_message = create_starboard_message(message, &tally);
+
+            http.update_message(board.channel_id, starboard_message_id)
+                .allowed_mentions(Some(&AllowedMentions::default()))
+                .content(Some(&new_message.content))
+                .change_context(ReactionError::ContentResponseTooLong)?
+                .embeds(Some(&new_message.embeds))
+                .change_context(ReactionError::StarboardMessage)?
+                .await
+                .change_context(ReactionError::StarboardMessage)?;
+
+            sqlx::query!(
+                r#"
+UPDATE starboard
+SET channel_id = ?, author_id = ?, emoji = ?, reaction_count = ?, message_timestamp = ?
+WHERE message_id = ? AND board = ?
+		"#,
+                channel_id_str,
+                author_id,
+                emoji,
+                reaction_count,
+                message_timestamp,
+                message_id_str,
+                board.name
+            )
+            .execute(&mut *pool)
+            .await
+            .change_context(ReactionError::PreviousReactionCount)?;
+
+            continue;
+        }
+
+        // check if not enough reactions were done to make a starboard post
+        if tally.count < board.reaction_requirement.into() {
+            continue;
+        }
+
+        // add to starboard!
+        let starboard_message = create_starboard_message(message, &tally);
+        let starboard_message = http
+            .create_message(board.channel_id)
+            .allowed_mentions(Some(&AllowedMentions::default()))
+            .content(&starboard_message.content)
+            .change_context(ReactionError::ContentResponseTooLong)?
+            .embeds(&starboard_message.embeds)
+            .change_context(ReactionError::StarboardMessage)?
+            .await
+            .change_context(ReactionError::StarboardMessage)?
+            .model()
+            .await
+            .change_context(ReactionError::StarboardMessage)?;
+
+        let starboard_message_id = starboard_message.id.to_string();
+
+        sqlx::query!(
+            r#"
+INSERT INTO starboard (starboard_id, message_id, board, channel_id, author_id, emoji, reaction_count, message_timestamp)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+		"#,
+            starboard_message_id,
+            message_id_str,
+            board.name,
+            channel_id_str,
+            author_id,
+            emoji,
+            reaction_count,
+            message_timestamp
+        )
+        .execute(&mut *pool)
+        .await
+        .change_context(ReactionError::PreviousReactionCount)?;
+    }
+
+    Ok(())
+}
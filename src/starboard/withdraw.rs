@@ -0,0 +1,174 @@
+use error_stack::{Report, ResultExt};
+use sqlx::{SqliteConnection, SqlitePool};
+use twilight_http::{Client, Error as HttpError};
+use twilight_model::{
+    channel::message::AllowedMentions,
+    id::{
+        marker::{ChannelMarker, MessageMarker},
+        Id,
+    },
+};
+
+use crate::{
+    config::{ApplicationConfig, DeletedMessagePolicy},
+    create_starboard_message::create_deleted_starboard_message,
+    error::{is_not_found, MessageError},
+};
+
+use super::lock::lock_message;
+
+/// Withdraws every starboard post of a message that has been deleted, following the configured
+/// [`DeletedMessagePolicy`], and forgets about its starboard entries.
+///
+/// If the deleted message was itself a starboard post, its entry is forgotten so that it is not
+/// updated again.
+pub async fn withdraw_starboard(
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    http: &Client,
+    pool: &SqlitePool,
+    config: &ApplicationConfig,
+) -> Result<(), Report<MessageError>> {
+    let _lock = lock_message(message_id).await;
+
+    let mut pool = pool
+        .acquire()
+        .await
+        .change_context(MessageError::DatabaseConnect)?;
+
+    let message_id_str = message_id.to_string();
+
+    // a moderator may have deleted the starboard post directly
+    sqlx::query!(
+        r#"
+DELETE FROM starboard
+WHERE starboard_id = ?
+	"#,
+        message_id_str
+    )
+    .execute(&mut *pool)
+    .await
+    .change_context(MessageError::StarboardEntry)?;
+
+    let withdrawn = remove_starboard_posts(
+        channel_id,
+        message_id,
+        config.starboard_deleted_message_policy,
+        http,
+        &mut pool,
+        config,
+    )
+    .await?;
+
+    if withdrawn {
+        log::info!("message {message_id} was deleted, withdrew it from the starboard");
+    }
+
+    Ok(())
+}
+
+/// Removes every starboard post of a message following the `policy`, and forgets about its
+/// starboard entries.
+///
+/// Posts that are already gone are forgotten as well. A post that fails to be removed is logged
+/// and its entry kept, without holding up the posts on other boards.
+///
+/// Returns whether the message had been posted to any starboard.
+pub(super) async fn remove_starboard_posts(
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    policy: DeletedMessagePolicy,
+    http: &Client,
+    connection: &mut SqliteConnection,
+    config: &ApplicationConfig,
+) -> Result<bool, Report<MessageError>> {
+    let message_id_str = message_id.to_string();
+
+    let starboard_ids = sqlx::query!(
+        r#"
+SELECT board, starboard_id
+FROM starboard
+WHERE message_id = ?
+	"#,
+        message_id_str
+    )
+    .fetch_all(&mut *connection)
+    .await
+    .change_context(MessageError::StarboardEntry)?;
+
+    if starboard_ids.is_empty() {
+        return Ok(false);
+    }
+
+    for row in starboard_ids {
+        if let Some(board) = config.starboard(&row.board) {
+            let starboard_message_id: Id<MessageMarker> = Id::new(
+                row.starboard_id
+                    .try_into()
+                    .change_context(MessageError::StarboardEntry)?,
+            );
+
+            let removed = match policy {
+                DeletedMessagePolicy::Delete => http
+                    .delete_message(board.channel_id, starboard_message_id)
+                    .await
+                    .map(|_| ())
+                    .change_context(MessageError::StarboardMessage),
+                DeletedMessagePolicy::Tombstone => {
+                    let tombstone = create_deleted_starboard_message(channel_id);
+
+                    async {
+                        http.update_message(board.channel_id, starboard_message_id)
+                            .allowed_mentions(Some(&AllowedMentions::default()))
+                            .content(Some(&tombstone.content))
+                            .change_context(MessageError::StarboardMessage)?
+                            .embeds(Some(&tombstone.embeds))
+                            .change_context(MessageError::StarboardMessage)?
+                            .await
+                            .change_context(MessageError::StarboardMessage)?;
+
+                        Ok::<_, Report<MessageError>>(())
+                    }
+                    .await
+                }
+            };
+
+            match removed {
+                Ok(()) => {}
+                // a moderator may have deleted the post already, or its channel is gone
+                Err(report) if report.downcast_ref::<HttpError>().is_some_and(is_not_found) => {
+                    log::debug!(
+                        "the {} starboard post of message {message_id} was already removed",
+                        row.board
+                    );
+                }
+                Err(report) => {
+                    log::error!(
+                        "Failed to withdraw message {message_id} from the {} starboard: {report:?}",
+                        row.board
+                    );
+                    continue;
+                }
+            }
+        } else {
+            log::warn!(
+                "message {message_id} was posted to the {} starboard, which is no longer configured",
+                row.board
+            );
+        }
+
+        sqlx::query!(
+            r#"
+DELETE FROM starboard
+WHERE message_id = ? AND board = ?
+	"#,
+            message_id_str,
+            row.board
+        )
+        .execute(&mut *connection)
+        .await
+        .change_context(MessageError::StarboardEntry)?;
+    }
+
+    Ok(true)
+}
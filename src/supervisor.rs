@@ -0,0 +1,121 @@
+use std::{
+    error::Error,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use error_stack::Report;
+use twilight_http::Client;
+use twilight_model::{
+    channel::message::AllowedMentions,
+    id::{marker::ChannelMarker, Id},
+};
+
+/// How long to wait before restarting a task after its first failure. The wait doubles with each
+/// failure in a row.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(5);
+
+/// The longest to wait before restarting a task.
+const MAX_BACKOFF: Duration = Duration::from_secs(10 * 60);
+
+/// How long a task must run before it is considered healthy again, forgiving its earlier failures.
+const HEALTHY_RUNTIME: Duration = Duration::from_secs(30 * 60);
+
+/// The amount of failures in a row after which a task is reported to the admin channel.
+const REPORT_THRESHOLD: u32 = 3;
+
+/// The most characters of a message Discord allows.
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// Runs a background task for as long as the bot runs, restarting it whenever it fails or panics.
+///
+/// Restarts are delayed by an exponentially increasing backoff while the task keeps failing. Once
+/// it has failed [`REPORT_THRESHOLD`] times in a row, the failure is reported to the
+/// `admin_channel_id`, if one is configured, so that it does not go unnoticed.
+pub fn supervise<T, F, C>(
+    name: &'static str,
+    http: Arc<Client>,
+    admin_channel_id: Option<Id<ChannelMarker>>,
+    task: T,
+) where
+    T: Fn() -> F + Send + 'static,
+    F: Future<Output = Result<(), Report<C>>> + Send + 'static,
+    C: Error + Send + Sync + 'static,
+{
+    tokio::spawn(async move {
+        let mut total_failures: u64 = 0;
+        let mut failures_in_a_row: u32 = 0;
+
+        loop {
+            let started = Instant::now();
+
+            // run the task on its own, so that a panic only takes down the task
+            let failure = match tokio::spawn(task()).await {
+                Ok(Ok(())) => {
+                    log::debug!("The {name} task completed");
+                    return;
+                }
+                Ok(Err(report)) => format!("{report:?}"),
+                Err(join_error) => format!("{join_error}"),
+            };
+
+            // a task that ran for a while before failing was healthy in the meantime
+            if started.elapsed() >= HEALTHY_RUNTIME {
+                failures_in_a_row = 0;
+            }
+            total_failures += 1;
+            failures_in_a_row += 1;
+
+            let backoff = INITIAL_BACKOFF
+                .saturating_mul(2u32.saturating_pow(failures_in_a_row - 1))
+                .min(MAX_BACKOFF);
+            log::error!(
+                "The {name} task failed ({failures_in_a_row} times in a row, {total_failures} in total), restarting in {} seconds: {failure}",
+                backoff.as_secs()
+            );
+
+            if failures_in_a_row == REPORT_THRESHOLD {
+                if let Some(channel_id) = admin_channel_id {
+                    report_failure(name, failures_in_a_row, &failure, &http, channel_id).await;
+                }
+            }
+
+            tokio::time::sleep(backoff).await;
+        }
+    });
+}
+
+/// Lets the admins know that a task keeps failing.
+async fn report_failure(
+    name: &str,
+    failures_in_a_row: u32,
+    failure: &str,
+    http: &Client,
+    channel_id: Id<ChannelMarker>,
+) {
+    let header = format!(
+        "The {name} task has failed {failures_in_a_row} times in a row, and keeps being restarted.\n"
+    );
+    // leave room for the header and the code block around the failure
+    let failure = failure
+        .chars()
+        .take(MAX_MESSAGE_LENGTH - header.chars().count() - 8)
+        .collect::<String>();
+    let content = format!("{header}```\n{failure}\n```");
+
+    let result = match http
+        .create_message(channel_id)
+        .allowed_mentions(Some(&AllowedMentions::default()))
+        .content(&content)
+    {
+        Ok(request) => request.await.map(|_| ()).map_err(|error| error.to_string()),
+        Err(error) => Err(error.to_string()),
+    };
+
+    if let Err(error) = result {
+        log::error!(
+            "Failed to report the failure of the {name} task to the admin channel: {error}"
+        );
+    }
+}